@@ -0,0 +1,56 @@
+use bank::storage::{
+    Storage,
+    export::Exporter,
+    import::{Importer, UnsettledPolicy, UserMap},
+};
+use parsers::{Format, ParseMode, RecordWriter, compress};
+use std::env;
+
+/// Заполняет банк записями из файла YPBank и выгружает историю обратно:
+/// `cargo run -p bank --example import -- data/records_example.csv history.bin`.
+/// Пути `.gz` и `.zst` распаковываются и сжимаются
+fn main() {
+    let mut args = env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or("data/records_example.csv".to_string());
+    let format = Format::from_path(&path).unwrap_or(Format::Csv);
+    let input = compress::open(&path).expect("Не удалось открыть файл");
+    let mut reader = format.reader(input);
+
+    let mut storage = Storage::new();
+    let users = UserMap::new().with_prefix("user_");
+    let report = Importer::new(users.clone())
+        .auto_create(true)
+        .unsettled(UnsettledPolicy::Record)
+        .mode(ParseMode::Lenient)
+        .import(&mut storage, &mut reader)
+        .unwrap_or_else(|err| panic!("{}", err));
+
+    for err in &report.errors {
+        eprintln!("{}", err);
+    }
+    println!(
+        "Применено: {}, в истории: {}, пропущено: {}, ошибок: {}, счетов: {}",
+        report.applied,
+        report.recorded,
+        report.skipped,
+        report.errors.len(),
+        storage.get_all().len()
+    );
+
+    let Some(out) = args.next() else {
+        return;
+    };
+    let format = Format::from_path(&out).unwrap_or(Format::Csv);
+    let mut output = compress::create(&out).expect("Не удалось создать файл");
+    let mut writer = format.writer(&mut output);
+    let report = Exporter::new(users)
+        .signed(format == Format::Bin)
+        .export(&storage, &mut writer)
+        .unwrap_or_else(|err| panic!("{}", err));
+    writer.finish().expect("Не удалось записать файл");
+    drop(writer);
+    output.finish().expect("Не удалось записать файл");
+    println!("Выгружено записей: {}", report.written);
+}
@@ -1,4 +1,5 @@
 use super::{BalanceSize, errors::BalanceError, operations::Operation};
+use parsers::ParseMode;
 use std::fmt::Display;
 
 /// Баланс
@@ -48,25 +49,7 @@ impl TryFrom<String> for Balance {
     type Error = BalanceError;
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
-        let (value, history) = value
-            .split_once(',')
-            .ok_or(BalanceError::InvalidParseBalance(
-                "Нет баланса.".to_string(),
-            ))?;
-
-        let value = value
-            .parse::<BalanceSize>()
-            .map_err(|_| BalanceError::InvalidParseBalance(value.to_string()))?;
-
-        let len_history = history.len();
-        let history = history[1..len_history - 1]
-            .split('|')
-            .map(|op| {
-                Operation::try_from(op.to_string())
-                    .map_err(|err| BalanceError::InvalidParseOperation(err))
-            })
-            .collect::<Result<Vec<Operation>, BalanceError>>()?;
-        Ok(Balance { value, history })
+        Balance::parse(&value, ParseMode::Strict).map(|(balance, _)| balance)
     }
 }
 
@@ -75,6 +58,42 @@ impl Balance {
         Balance { value, history }
     }
 
+    /// Разбирает баланс вида `значение,[операция|операция]`.
+    /// В нестрогом режиме неверные операции пропускаются и возвращаются вторым элементом,
+    /// ошибка в значении или скобках истории прерывает разбор в любом режиме
+    pub fn parse(value: &str, mode: ParseMode) -> Result<(Self, Vec<BalanceError>), BalanceError> {
+        let (value, history) = value.split_once(',').ok_or(BalanceError::MissingHistory)?;
+
+        let value = value
+            .parse::<BalanceSize>()
+            .map_err(|_| BalanceError::InvalidParseBalance(value.to_string()))?;
+
+        let history = history
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .ok_or_else(|| BalanceError::InvalidHistory(history.to_string()))?;
+
+        let mut balance = Balance::new(value, vec![]);
+        let mut errors = Vec::new();
+        if history.is_empty() {
+            return Ok((balance, errors));
+        }
+
+        for (index, op) in history.split('|').enumerate() {
+            match Operation::try_from(op.to_string()) {
+                Ok(op) => balance.history.push(op),
+                Err(error) => {
+                    let error = BalanceError::InvalidParseOperation { index, error };
+                    if mode == ParseMode::Strict {
+                        return Err(error);
+                    }
+                    errors.push(error);
+                }
+            }
+        }
+        Ok((balance, errors))
+    }
+
     pub fn get_value(&self) -> BalanceSize {
         self.value
     }
@@ -83,6 +102,11 @@ impl Balance {
         &self.history
     }
 
+    /// Добавляет операцию в историю, не меняя баланс
+    pub(crate) fn record(&mut self, op: Operation) {
+        self.history.push(op);
+    }
+
     pub(crate) fn save(&self) -> String {
         let history = self
             .history
@@ -97,6 +121,22 @@ impl Balance {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::balance::{operations::OperationError, strategies::balance};
+    use assert_matches::assert_matches;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_balance_save_load(balance in balance()) {
+            let loaded = Balance::try_from(balance.save()).unwrap();
+            prop_assert_eq!(loaded, balance);
+        }
+
+        #[test]
+        fn prop_balance_never_panics(text in "\\PC*") {
+            let _ = Balance::parse(&text, ParseMode::Lenient);
+        }
+    }
 
     #[test]
     fn test_balance_try_from() {
@@ -112,6 +152,49 @@ mod tests {
         assert!(balance.is_err());
     }
 
+    #[test]
+    fn test_balance_try_from_empty_history() {
+        let balance = Balance::try_from("100,[]".to_string()).unwrap();
+        assert_eq!(balance, Balance::new(100, vec![]));
+        assert_eq!(balance.save(), "100,[]");
+    }
+
+    #[test]
+    fn test_balance_try_from_operation_error() {
+        let balance = "100,[1,1764444526,D100,success,Record number #1|3,x,C,success,Record]";
+        assert_matches!(
+            Balance::try_from(balance.to_string()),
+            Err(BalanceError::InvalidParseOperation {
+                index: 1,
+                error: OperationError::ParseError {
+                    field: "TIMESTAMP",
+                    ..
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_balance_parse_lenient() {
+        let balance = "100,[1,1764444526,D100,success,Record|2,x,C,success,Record|3,1764444535,C,done,Record]";
+        let (parsed, errors) = Balance::parse(balance, ParseMode::Lenient).unwrap();
+        assert_eq!(parsed.get_value(), 100);
+        assert_eq!(parsed.get_history().len(), 1);
+        let indexes: Vec<usize> = errors
+            .iter()
+            .map(|e| match e {
+                BalanceError::InvalidParseOperation { index, .. } => *index,
+                _ => usize::MAX,
+            })
+            .collect();
+        assert_eq!(indexes, vec![1, 2]);
+
+        assert_matches!(
+            Balance::parse("x,[]", ParseMode::Lenient),
+            Err(BalanceError::InvalidParseBalance(_))
+        );
+    }
+
     #[test]
     fn test_balance_load_save() {
         let balance = Balance::try_from("100,[1,1764444526,D100,success,Record number #1|3,1764444535,T(Julia:200:true),success,Record number #3]".to_string());
@@ -1,7 +1,17 @@
 use super::operations::OperationError;
 
+/// Ошибки разбора баланса
 #[derive(Debug)]
 pub enum BalanceError {
-    InvalidParseOperation(OperationError),
+    /// Ошибка в операции истории с номером `index` (с 0)
+    InvalidParseOperation { index: usize, error: OperationError },
+
+    /// Неверное значение баланса
     InvalidParseBalance(String),
+
+    /// Нет истории после баланса
+    MissingHistory,
+
+    /// История не заключена в `[...]`
+    InvalidHistory(String),
 }
@@ -4,6 +4,9 @@ pub mod errors;
 pub mod manager;
 pub mod operations;
 
+#[cfg(test)]
+pub(crate) mod strategies;
+
 pub use balance::Balance;
 
 pub type BalanceSize = i128;
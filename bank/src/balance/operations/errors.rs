@@ -15,8 +15,11 @@ pub enum OperationError {
     /// Неверный статус
     InvalidStatus,
 
-    /// Ошибка парсинга
-    ParseError(String),
+    /// Неверное значение поля при разборе
+    ParseError { field: &'static str, value: String },
+
+    /// Нет поля при разборе
+    MissingField(&'static str),
 
     /// Перевышен лимит
     OverLimitSize,
@@ -4,6 +4,6 @@ mod status;
 mod types;
 
 pub use errors::OperationError;
-pub use operations::Operation;
+pub use operations::{FIELDS, Operation};
 pub use status::Status as OperationStatus;
 pub use types::{OperationAmount, OperationType};
@@ -4,6 +4,9 @@ use crate::Name;
 use crate::balance::BalanceSize;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+/// Поля операции в порядке записи в файле
+pub const FIELDS: [&str; 5] = ["ID", "TIMESTAMP", "TYPE", "STATUS", "DESCRIPTION"];
+
 /// Операция баланса
 #[derive(Debug, Clone, PartialEq)]
 pub struct Operation {
@@ -51,6 +54,15 @@ impl Operation {
         Self::new(id, OperationType::Close, None)
     }
 
+    pub fn get_id(&self) -> u64 {
+        self.id
+    }
+
+    /// Время операции в секундах от эпохи Unix
+    pub fn get_timestamp(&self) -> u64 {
+        self.timestamp
+    }
+
     /// Устанавливает статус операции
     pub fn set_status(&mut self, status: OperationStatus) {
         self.status = status;
@@ -118,11 +130,11 @@ impl Operation {
     }
 }
 
-impl Into<String> for Operation {
-    fn into(self) -> String {
+impl From<Operation> for String {
+    fn from(op: Operation) -> Self {
         format!(
             "{},{},{},{},{}",
-            self.id, self.timestamp, self.tx_type, self.status, self.description
+            op.id, op.timestamp, op.tx_type, op.status, op.description
         )
     }
 }
@@ -141,41 +153,22 @@ impl TryFrom<String> for Operation {
 
     fn try_from(value: String) -> Result<Self, Self::Error> {
         let mut parts = value.split(',');
-        let id = parts
-            .next()
-            .ok_or(OperationError::ParseError("Нет айди операции".to_string()))?
-            .parse::<u64>()
-            .map_err(|_| OperationError::ParseError("Айди операции неверный".to_string()))?;
-        let timestamp = parts
-            .next()
-            .ok_or(OperationError::ParseError(
-                "Нет времени операции".to_string(),
-            ))?
-            .parse::<u64>()
-            .map_err(|_| OperationError::ParseError("Время операции неверный".to_string()))?;
-
-        let part_tx_type = parts
-            .next()
-            .ok_or(OperationError::ParseError(
-                "Тип операции не определен".to_string(),
-            ))?
-            .to_string();
-
-        let tx_type = OperationType::try_from(part_tx_type)?;
-
-        let part_status = parts
-            .next()
-            .ok_or(OperationError::ParseError(
-                "Статус операции не определен".to_string(),
-            ))?
-            .to_string();
-        let status = OperationStatus::try_from(part_status)?;
-        let description = parts
-            .next()
-            .ok_or(OperationError::ParseError(
-                "Нет описания операции".to_string(),
-            ))?
-            .to_string();
+        let mut next =
+            |field: &'static str| parts.next().ok_or(OperationError::MissingField(field));
+        let number = |field: &'static str, value: &str| {
+            value
+                .parse::<u64>()
+                .map_err(|_| OperationError::ParseError {
+                    field,
+                    value: value.to_string(),
+                })
+        };
+
+        let id = number("ID", next("ID")?)?;
+        let timestamp = number("TIMESTAMP", next("TIMESTAMP")?)?;
+        let tx_type = OperationType::try_from(next("TYPE")?.to_string())?;
+        let status = OperationStatus::try_from(next("STATUS")?.to_string())?;
+        let description = next("DESCRIPTION")?.to_string();
 
         Ok(Operation {
             id,
@@ -190,6 +183,20 @@ impl TryFrom<String> for Operation {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::balance::strategies::operation;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn prop_operation_save_load(op in operation()) {
+            prop_assert_eq!(Operation::try_from(String::from(&op)), Ok(op));
+        }
+
+        #[test]
+        fn prop_operation_never_panics(text in "\\PC*") {
+            let _ = Operation::try_from(text);
+        }
+    }
 
     #[test]
     fn test_balance_op_apply_deposit() {
@@ -35,9 +35,9 @@ impl Debug for OperationType {
     }
 }
 
-impl Into<String> for OperationType {
-    fn into(self) -> String {
-        match self {
+impl From<OperationType> for String {
+    fn from(op: OperationType) -> Self {
+        match op {
             OperationType::Deposit(v) => format!("D{}", v),
             OperationType::Withdraw(v) => format!("W{}", v),
             OperationType::Transfer(n, v, f) => format!("T({}:{}:{})", n, v, f),
@@ -50,37 +50,77 @@ impl TryFrom<String> for OperationType {
     type Error = OperationError;
 
     fn try_from(text: String) -> Result<Self, Self::Error> {
-        if text.len() < 2 && text != "C" {
-            return Err(OperationError::ParseError(text));
+        parse(&text).ok_or(OperationError::ParseError {
+            field: "TYPE",
+            value: text,
+        })
+    }
+}
+
+/// Разбирает тип операции: `C`, `D100`, `W100` или `T(имя:100:true)`
+fn parse(text: &str) -> Option<OperationType> {
+    if text == "C" {
+        return Some(OperationType::Close);
+    }
+
+    let mut chars = text.chars();
+    let op = chars.next()?;
+    let val = chars.as_str();
+    match op {
+        'D' => val.parse().ok().map(OperationType::Deposit),
+        'W' => val.parse().ok().map(OperationType::Withdraw),
+        'T' => {
+            let inner = val.strip_prefix('(')?.strip_suffix(')')?;
+            let [name, value, flag] = inner.splitn(3, ':').collect::<Vec<&str>>()[..] else {
+                return None;
+            };
+            let flag = match flag {
+                "true" => true,
+                "false" => false,
+                _ => return None,
+            };
+            Some(OperationType::Transfer(
+                name.to_string(),
+                value.parse().ok()?,
+                flag,
+            ))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::balance::strategies::operation_type;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_operation_type_try_from() {
+        let parse = |text: &str| OperationType::try_from(text.to_string());
+        assert_eq!(parse("C"), Ok(OperationType::Close));
+        assert_eq!(parse("D100"), Ok(OperationType::Deposit(100)));
+        assert_eq!(
+            parse("T(Julia:200:true)"),
+            Ok(OperationType::Transfer("Julia".to_string(), 200, true))
+        );
+
+        // Раньше эти строки приводили к панике при срезе
+        for text in ["", "Tx", "T(", "Ж5", "DЖ", "T)", "T()"] {
+            assert!(parse(text).is_err(), "{:?}", text);
         }
-        if text.len() == 1 && text == "C" {
-            return Ok(OperationType::Close);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_operation_type_never_panics(text in "\\PC*") {
+            let _ = OperationType::try_from(text);
         }
 
-        let (op, val) = text.split_at(1);
-        let val_len = val.len();
-        if let Ok(v) = val.parse::<u64>() {
-            return match op {
-                "D" => Ok(OperationType::Deposit(v)),
-                "W" => Ok(OperationType::Withdraw(v)),
-                _ => Err(OperationError::InvalidOperation(text)),
-            };
-        } else {
-            let parts: Vec<&str> = val[1..val_len - 1].splitn(3, ':').collect();
-            if let [name, value, flag] = parts.as_slice() {
-                let value = value
-                    .parse::<u64>()
-                    .map_err(|_| OperationError::ParseError(val.to_string()))?;
-                if !(*flag == "false" || *flag == "true") {
-                    return Err(OperationError::ParseError(text));
-                }
-                let flag = *flag == "true";
-                return match op {
-                    "T" => Ok(OperationType::Transfer(name.to_string(), value, flag)),
-                    _ => Err(OperationError::InvalidOperation(text)),
-                };
-            }
-            Err(OperationError::ParseError(text))
+        #[test]
+        fn prop_operation_type_round_trip(op in operation_type()) {
+            let text = format!("{:?}", op);
+            prop_assert_eq!(OperationType::try_from(text), Ok(op));
         }
     }
 }
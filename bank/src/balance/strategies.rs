@@ -0,0 +1,49 @@
+//! Стратегии proptest для операций и балансов
+
+use super::{
+    Balance, BalanceSize,
+    operations::{Operation, OperationStatus, OperationType},
+};
+use proptest::prelude::*;
+
+/// Имя счёта без разделителей формата хранения
+pub fn name() -> impl Strategy<Value = String> {
+    "[A-Za-zА-Яа-я0-9_]{1,12}"
+}
+
+pub fn operation_type() -> impl Strategy<Value = OperationType> {
+    prop_oneof![
+        Just(OperationType::Close),
+        any::<u64>().prop_map(OperationType::Deposit),
+        any::<u64>().prop_map(OperationType::Withdraw),
+        (name(), any::<u64>(), any::<bool>())
+            .prop_map(|(name, amount, is_to)| OperationType::Transfer(name, amount, is_to)),
+    ]
+}
+
+pub fn status() -> impl Strategy<Value = OperationStatus> {
+    prop_oneof![
+        Just(OperationStatus::SUCCESS),
+        Just(OperationStatus::FAILURE),
+        Just(OperationStatus::PENDING),
+    ]
+}
+
+/// Операция. Описание без `,` и `|`: они разделяют поля и операции при сохранении
+pub fn operation() -> impl Strategy<Value = Operation> {
+    (any::<u64>(), operation_type(), status(), "[^,|\n\r]{0,20}").prop_map(
+        |(id, tx_type, status, description)| {
+            let mut op = Operation::new(id, tx_type, Some(description));
+            op.set_status(status);
+            op
+        },
+    )
+}
+
+pub fn balance() -> impl Strategy<Value = Balance> {
+    (
+        any::<BalanceSize>(),
+        prop::collection::vec(operation(), 0..5),
+    )
+        .prop_map(|(value, history)| Balance::new(value, history))
+}
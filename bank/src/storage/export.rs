@@ -0,0 +1,248 @@
+//! Выгрузка истории операций [Storage] в файлы YPBank
+
+use super::{Storage, import::UserMap};
+use crate::{
+    Name,
+    balance::operations::{Operation, OperationStatus, OperationType},
+};
+use parsers::{ParseError, RecordWriter, TxRecord, TxStatus, TxType};
+use std::{collections::HashSet, fmt::Display};
+
+/// Ошибка выгрузки
+#[derive(Debug)]
+pub enum ExportError {
+    /// Имени счёта нет в [UserMap]
+    UnmappedName(Name),
+
+    /// Сумма операции не помещается в AMOUNT
+    AmountOverflow { tx_id: u64 },
+
+    /// Ошибка записи файла
+    Write(Box<ParseError>),
+}
+
+impl Display for ExportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExportError::UnmappedName(name) => write!(f, "нет USER_ID для счёта {}", name),
+            ExportError::AmountOverflow { tx_id } => {
+                write!(f, "сумма операции {} не помещается в AMOUNT", tx_id)
+            }
+            ExportError::Write(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<ParseError> for ExportError {
+    fn from(err: ParseError) -> Self {
+        ExportError::Write(Box::new(err))
+    }
+}
+
+/// Итоги выгрузки
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ExportReport {
+    /// Записано записей
+    pub written: u64,
+    /// Операции закрытия счёта: в YPBank для них нет типа
+    pub skipped: u64,
+}
+
+/// Превращает историю операций счетов в записи YPBank.
+///
+/// TX_ID берётся из id операции. Перевод хранится у обоих счетов,
+/// в выгрузку попадает одна запись TRANSFER на id.
+#[derive(Debug, Clone, Default)]
+pub struct Exporter {
+    users: UserMap,
+    signed: bool,
+}
+
+impl Exporter {
+    pub fn new(users: UserMap) -> Self {
+        Self {
+            users,
+            signed: false,
+        }
+    }
+
+    /// Списания с отрицательным AMOUNT, как требует формат `bin`
+    pub fn signed(mut self, signed: bool) -> Self {
+        self.signed = signed;
+        self
+    }
+
+    /// Записи по всей истории хранилища, упорядоченные по TX_ID
+    pub fn records(&self, storage: &Storage) -> Result<(Vec<TxRecord>, ExportReport), ExportError> {
+        let mut report = ExportReport::default();
+        let mut transfers = HashSet::new();
+        let mut records = Vec::new();
+
+        for (name, balance) in storage.get_all() {
+            for op in balance.get_history() {
+                if let OperationType::Transfer(..) = op.tx_type
+                    && !transfers.insert(op.get_id())
+                {
+                    continue;
+                }
+                match self.record(&name, op)? {
+                    Some(record) => records.push(record),
+                    None => report.skipped += 1,
+                }
+            }
+        }
+
+        records.sort_by_key(|record| record.tx_id);
+        report.written = records.len() as u64;
+        Ok((records, report))
+    }
+
+    /// Пишет историю хранилища в `writer`. `writer.finish` остаётся за вызывающим
+    pub fn export<W: RecordWriter + ?Sized>(
+        &self,
+        storage: &Storage,
+        writer: &mut W,
+    ) -> Result<ExportReport, ExportError> {
+        let (records, report) = self.records(storage)?;
+        writer.write_all(&records)?;
+        Ok(report)
+    }
+
+    fn user_id(&self, name: &str) -> Result<u64, ExportError> {
+        self.users
+            .id(name)
+            .ok_or_else(|| ExportError::UnmappedName(name.to_string()))
+    }
+
+    /// Запись по операции счёта `name`, `None` - для операции нет типа YPBank
+    fn record(&self, name: &str, op: &Operation) -> Result<Option<TxRecord>, ExportError> {
+        let (tx_type, from_user_id, to_user_id, amount) = match &op.tx_type {
+            OperationType::Deposit(amount) => (TxType::Deposit, 0, self.user_id(name)?, *amount),
+            OperationType::Withdraw(amount) => {
+                (TxType::Withdrawal, self.user_id(name)?, 0, *amount)
+            }
+            OperationType::Transfer(other, amount, is_to) => {
+                let (from, to) = if *is_to {
+                    (other.as_str(), name)
+                } else {
+                    (name, other.as_str())
+                };
+                (
+                    TxType::Transfer,
+                    self.user_id(from)?,
+                    self.user_id(to)?,
+                    *amount,
+                )
+            }
+            OperationType::Close => return Ok(None),
+        };
+
+        let tx_id = op.get_id();
+        let amount = i64::try_from(amount).map_err(|_| ExportError::AmountOverflow { tx_id })?;
+        let amount = if self.signed && tx_type == TxType::Withdrawal {
+            -amount
+        } else {
+            amount
+        };
+        let status = match op.status {
+            OperationStatus::SUCCESS => TxStatus::Success,
+            OperationStatus::FAILURE => TxStatus::Failure,
+            OperationStatus::PENDING => TxStatus::Pending,
+        };
+
+        Ok(Some(TxRecord {
+            tx_id,
+            tx_type,
+            from_user_id,
+            to_user_id,
+            amount,
+            timestamp: op.get_timestamp() * 1000,
+            status,
+            description: op.description.clone(),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::balance::manager::BalanceManager;
+    use assert_matches::assert_matches;
+    use parsers::{
+        RecordReader,
+        formats::csv::{CsvReader, CsvWriter},
+    };
+
+    fn storage() -> Storage {
+        let mut storage = Storage::new();
+        let ivan = "user_1".to_string();
+        let julia = "user_2".to_string();
+        storage.add_user(ivan.clone());
+        storage.add_user(julia.clone());
+        storage.deposit(&ivan, 500).unwrap();
+        storage.transfer(&ivan, &julia, 200).unwrap();
+        storage.withdraw(&julia, 50).unwrap();
+        storage.withdraw(&julia, 1000).unwrap_err();
+        storage
+    }
+
+    #[test]
+    fn test_export_records() {
+        let (records, report) = Exporter::new(UserMap::new().with_prefix("user_"))
+            .signed(true)
+            .records(&storage())
+            .unwrap();
+
+        assert_eq!(
+            report,
+            ExportReport {
+                written: 4,
+                skipped: 0
+            }
+        );
+        let fields: Vec<(u64, TxType, u64, u64, i64, TxStatus)> = records
+            .iter()
+            .map(|r| {
+                (
+                    r.tx_id,
+                    r.tx_type,
+                    r.from_user_id,
+                    r.to_user_id,
+                    r.amount,
+                    r.status,
+                )
+            })
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                (1, TxType::Deposit, 0, 1, 500, TxStatus::Success),
+                (2, TxType::Transfer, 1, 2, 200, TxStatus::Success),
+                (3, TxType::Withdrawal, 2, 0, -50, TxStatus::Success),
+                (4, TxType::Withdrawal, 2, 0, -1000, TxStatus::Failure),
+            ]
+        );
+        assert_eq!(records[0].description, "Record number #1");
+    }
+
+    #[test]
+    fn test_export_csv_round_trip() {
+        let mut writer = CsvWriter::new(Vec::new());
+        Exporter::new(UserMap::new().with_prefix("user_"))
+            .export(&storage(), &mut writer)
+            .unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let records = CsvReader::new(data.as_slice()).read_all().unwrap();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records[3].amount, 1000);
+    }
+
+    #[test]
+    fn test_export_unmapped_name() {
+        let err = Exporter::new(UserMap::new())
+            .records(&storage())
+            .unwrap_err();
+        assert_matches!(err, ExportError::UnmappedName(_));
+    }
+}
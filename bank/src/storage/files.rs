@@ -1,11 +1,98 @@
 use super::Storage;
-use crate::balance::{Balance, errors::BalanceError};
+use crate::balance::{
+    Balance,
+    errors::BalanceError,
+    operations::{FIELDS, OperationError},
+};
+use parsers::{Line, ParseError, ParseErrorKind, ParseMode, ParseReport, compress};
 use std::{
-    fs::{self, File},
-    io::{self, BufRead},
-    path::Path,
+    io::{BufRead, Write},
+    ops::Range,
 };
 
+/// Переводит ошибку разбора баланса в ошибку с позицией в строке файла,
+/// `balance` - часть строки после `;`
+fn balance_error(line: &Line, balance: &str, err: BalanceError) -> ParseError {
+    let (value, history) = balance.split_once(',').unwrap_or((balance, ""));
+    match err {
+        BalanceError::MissingHistory => line
+            .error(ParseErrorKind::MissingField, balance)
+            .with_field("HISTORY"),
+        BalanceError::InvalidParseBalance(value_text) => line
+            .error(ParseErrorKind::InvalidValue(value_text), value)
+            .with_field("BALANCE"),
+        BalanceError::InvalidHistory(history_text) => line
+            .error(ParseErrorKind::InvalidValue(history_text), history)
+            .with_field("HISTORY"),
+        BalanceError::InvalidParseOperation { index, error } => {
+            let ops = history
+                .strip_prefix('[')
+                .and_then(|h| h.strip_suffix(']'))
+                .unwrap_or(history);
+            let op = ops.split('|').nth(index).unwrap_or(ops);
+            let part = |field: &str| {
+                FIELDS
+                    .iter()
+                    .position(|f| *f == field)
+                    .and_then(|i| op.split(',').nth(i))
+                    .unwrap_or(op)
+            };
+            match error {
+                OperationError::ParseError { field, value } => line
+                    .error(ParseErrorKind::InvalidValue(value), part(field))
+                    .with_field(field),
+                OperationError::InvalidStatus => {
+                    let status = part("STATUS");
+                    line.error(ParseErrorKind::InvalidValue(status.to_string()), status)
+                        .with_field("STATUS")
+                }
+                OperationError::MissingField(field) => line
+                    .error(ParseErrorKind::MissingField, op)
+                    .with_field(field),
+                _ => line
+                    .error(ParseErrorKind::InvalidValue(op.to_string()), op)
+                    .with_field("HISTORY"),
+            }
+        }
+    }
+}
+
+/// Часть строки `Имя;баланс,[история]` с неверным UTF-8 на смещении `at`
+fn utf8_field(text: &str, at: usize) -> Option<(&'static str, Range<usize>)> {
+    let name_end = text.find(';')?;
+    if at < name_end {
+        return Some(("NAME", 0..name_end));
+    }
+    let balance_end = text[name_end..].find(',').map_or(text.len(), |i| name_end + i);
+    if at < balance_end {
+        Some(("BALANCE", name_end + 1..balance_end))
+    } else {
+        Some(("HISTORY", balance_end + 1..text.len()))
+    }
+}
+
+/// Разбирает строку файла на имя и баланс, возвращает и ошибки пропущенных операций
+fn parse_line<'a>(
+    line: &Line<'a>,
+    mode: ParseMode,
+) -> Result<(&'a str, Balance, Vec<ParseError>), ParseError> {
+    let content = line.text.trim_start();
+
+    // Строка делится на имя и баланс: "Name;Balance"
+    let (name, balance) = content
+        .split_once(';')
+        .filter(|(_, balance)| !balance.contains(';'))
+        .ok_or_else(|| line.error(ParseErrorKind::InvalidLine, content))?;
+
+    let (parsed, errors) =
+        Balance::parse(balance, mode).map_err(|err| balance_error(line, balance, err))?;
+    let errors = errors
+        .into_iter()
+        .map(|err| balance_error(line, balance, err))
+        .collect();
+    Ok((name, parsed, errors))
+}
+
 impl Storage {
     fn set_balance(&mut self, name: &str, balance: Balance) {
         self.accounts
@@ -13,62 +100,67 @@ impl Storage {
             .and_modify(|b| *b = balance.clone())
             .or_insert(balance);
     }
-    pub fn load_data(file: &str) -> Result<Storage, std::io::Error> {
+
+    /// Загружает счета из файла строк вида `Имя;баланс,[операция|операция]`.
+    /// Ошибка содержит строку, столбец и фрагмент, где разбор не удался.
+    /// Файл, сжатый gzip или zstd, распаковывается на лету
+    pub fn load_data(file: &str) -> Result<Storage, ParseError> {
+        Self::load_data_with(file, ParseMode::Strict).map(|report| report.data)
+    }
+
+    /// Загружает счета в режиме `mode`. В нестрогом режиме неверные строки
+    /// и операции пропускаются, а их ошибки собираются в отчёт
+    pub fn load_data_with(file: &str, mode: ParseMode) -> Result<ParseReport<Storage>, ParseError> {
         let mut storage = Storage::new();
-        if !Path::new(file).exists() {
-            return Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Файл не найден",
-            ));
-        }
+        let mut errors = Vec::new();
+        let mut reader = compress::open(file)?;
+        let mut buf = Vec::new();
+        let mut number = 0;
 
-        let file = File::open(file)?;
-        let reader = io::BufReader::new(file);
-        for line in reader.lines() {
-            // Каждая строка — это Result<String>, поэтому делаем if let Ok
-            if let Ok(line) = line {
-                // Разделяем строку по запятой: "Name,Balance"
-                let parts: Vec<&str> = line.trim().split(';').collect();
-
-                if parts.len() == 2 {
-                    let name = parts[0].to_string();
-                    // Пробуем преобразовать баланс из строки в число
-                    let balance = Balance::try_from(parts[1].to_string()).map_err(|e| {
-                        let message = if let BalanceError::InvalidParseBalance(e) = e {
-                            format!("Неверный формат баланса: {}", e)
-                        } else {
-                            "Неверный формат операций".to_string()
-                        };
-                        std::io::Error::new(std::io::ErrorKind::InvalidData, message)
-                    })?;
-
-                    storage.add_user(name.clone());
-                    storage.set_balance(&name, balance);
-                } else {
-                    return Err(std::io::Error::new(
-                        std::io::ErrorKind::InvalidData,
-                        "Неверный формат строки",
-                    ));
+        // Строка с неверным UTF-8 - ошибка с позицией, а не ошибка ввода-вывода
+        loop {
+            buf.clear();
+            if reader.read_until(b'\n', &mut buf)? == 0 {
+                break;
+            }
+            number += 1;
+            let parsed = Line::decode_with(number, &buf, utf8_field)
+                .and_then(|line| parse_line(&Line::new(number, line.text.trim_end()), mode));
+            match parsed {
+                Ok((name, balance, line_errors)) => {
+                    storage.add_user(name.to_string());
+                    storage.set_balance(name, balance);
+                    errors.extend(line_errors);
                 }
+                Err(err) if mode == ParseMode::Lenient => errors.push(err),
+                Err(err) => return Err(err),
             }
         }
 
-        Ok(storage)
+        Ok(ParseReport {
+            data: storage,
+            errors,
+        })
     }
 
+    /// Сохраняет счета в файл, пути `.gz` и `.zst` сжимаются
     pub fn save(&self, file: &str) {
-        let mut data = String::new();
-        for (name, balance) in self.get_all() {
-            data.push_str(&format!("{};{}\n", name, balance.save()));
-        }
-        fs::write(file, data).expect("Не удалось записать файл");
+        let result = compress::create(file).and_then(|mut out| {
+            for (name, balance) in self.get_all() {
+                writeln!(out, "{};{}", name, balance.save())?;
+            }
+            out.finish()
+        });
+        result.expect("Не удалось записать файл");
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::io::Write;
+    use assert_matches::assert_matches;
+    use parsers::Position;
+    use std::io::{self, Write};
     use tempfile::NamedTempFile;
 
     #[test]
@@ -116,4 +208,153 @@ Julia;400,[2,1764444530,D600,success,Record number #2|3,1764444535,T(Ivan:200:fa
         let storage = Storage::load_data(path);
         assert!(storage.is_err());
     }
+
+    /// Загружает строки во временный файл и возвращает ошибку загрузки
+    fn load_error(data: &str) -> ParseError {
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", data).unwrap();
+        Storage::load_data(file.path().to_str().unwrap()).unwrap_err()
+    }
+
+    #[test]
+    fn test_load_data_missing_file() {
+        let err = Storage::load_data("/nonexistent/balance.csv").unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::Io(e) if e.kind() == io::ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn test_save_load_compressed() {
+        use crate::balance::manager::BalanceManager;
+
+        let mut storage = Storage::new();
+        storage.add_user("Ivan".to_string());
+        storage.add_user("Julia".to_string());
+        storage.deposit(&"Ivan".to_string(), 300).unwrap();
+        storage
+            .transfer(&"Ivan".to_string(), &"Julia".to_string(), 120)
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["balance.csv", "balance.csv.gz", "balance.csv.zst"] {
+            let path = dir.path().join(name);
+            let path = path.to_str().unwrap();
+            storage.save(path);
+
+            let loaded = Storage::load_data(path).unwrap();
+            for user in ["Ivan", "Julia"].map(String::from) {
+                assert_eq!(
+                    loaded.get_balance(&user),
+                    storage.get_balance(&user),
+                    "{}",
+                    name
+                );
+            }
+        }
+        let plain = std::fs::read(dir.path().join("balance.csv")).unwrap();
+        let packed = std::fs::read(dir.path().join("balance.csv.gz")).unwrap();
+        assert_ne!(plain, packed);
+    }
+
+    #[test]
+    fn test_load_data_error_position() {
+        let err = load_error(
+            "Ivan;300,[]\nJulia;400,[2,1764444530,D600,success,Record|3,1764444535,O5,done,Record]\n",
+        );
+        assert_matches!(&err.kind, ParseErrorKind::InvalidValue(value) if value == "O5");
+        assert_eq!(err.field, Some("TYPE"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 2,
+                column: 58
+            })
+        );
+        assert_eq!(
+            err.to_string(),
+            "ошибка: неверное значение поля TYPE: \"O5\"
+ --> строка 2, столбец 58
+  |
+2 | Julia;400,[2,1764444530,D600,success,Record|3,1764444535,O5,done,Record]
+  |                                                          ^^"
+        );
+
+        let err = load_error("Ivan;3x0,[]");
+        assert_eq!(err.field, Some("BALANCE"));
+        assert_eq!(err.position, Some(Position::Line { line: 1, column: 6 }));
+
+        let err = load_error("Ivan;300,[1,2,C,done,Record]");
+        assert_eq!(err.field, Some("STATUS"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 1,
+                column: 17
+            })
+        );
+
+        let err = load_error("Ivan;300,[1,2,C]");
+        assert_matches!(err.kind, ParseErrorKind::MissingField);
+        assert_eq!(err.field, Some("STATUS"));
+
+        let err = load_error("Ivan 300");
+        assert_matches!(err.kind, ParseErrorKind::InvalidLine);
+        assert_eq!(err.line(), Some(1));
+    }
+
+    #[test]
+    fn test_load_data_lenient() {
+        let data = "Ivan;300,[1,1764444526,D100,success,Record|2,x,C,success,Record]
+Broken line
+Julia;4x0,[]
+Petr;200,[]
+";
+        let mut file = NamedTempFile::new().unwrap();
+        write!(file, "{}", data).unwrap();
+        let path = file.path().to_str().unwrap();
+
+        assert!(Storage::load_data(path).is_err());
+
+        let report = Storage::load_data_with(path, ParseMode::Lenient).unwrap();
+        let storage = &report.data;
+        assert_eq!(storage.get_all().len(), 2);
+        let ivan = storage.get_balance(&"Ivan".to_string()).unwrap();
+        assert_eq!(ivan.get_history().len(), 1);
+        assert!(storage.get_balance(&"Julia".to_string()).is_none());
+
+        let errors: Vec<(Option<usize>, Option<&str>)> =
+            report.errors.iter().map(|e| (e.line(), e.field)).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (Some(1), Some("TIMESTAMP")),
+                (Some(2), None),
+                (Some(3), Some("BALANCE"))
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_data_invalid_utf8() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"Ivan;300,[]\nJu\xFFlia;400,[]\nPetr;200,[]\n")
+            .unwrap();
+        let path = file.path().to_str().unwrap();
+
+        let err = Storage::load_data(path).unwrap_err();
+        assert_eq!(err.position, Some(Position::Line { line: 2, column: 3 }));
+        assert_eq!(err.field, Some("NAME"));
+        assert_eq!(
+            err.to_string(),
+            "ошибка: неверное значение поля NAME: \"Ju\u{FFFD}lia\"
+ --> строка 2, столбец 3
+  |
+2 | Ju\u{FFFD}lia;400,[]
+  |   ^"
+        );
+
+        let report = Storage::load_data_with(path, ParseMode::Lenient).unwrap();
+        assert_eq!(report.data.get_all().len(), 2);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].line(), Some(2));
+    }
 }
@@ -0,0 +1,426 @@
+//! Загрузка файлов транзакций YPBank в [Storage]
+
+use super::Storage;
+use crate::{
+    Name,
+    balance::{
+        manager::{BalanceManager, BalanceManagerError},
+        operations::{Operation, OperationStatus},
+    },
+};
+use parsers::{ParseError, ParseMode, RecordReader, TxRecord, TxStatus, TxType};
+use std::{collections::HashMap, fmt::Display};
+
+/// Соответствие числовых USER_ID именам счетов
+#[derive(Debug, Clone, Default)]
+pub struct UserMap {
+    names: HashMap<u64, Name>,
+    /// Обратная таблица для [UserMap::id]; у имени нескольких USER_ID - последний заданный
+    ids: HashMap<Name, u64>,
+    prefix: Option<String>,
+}
+
+impl UserMap {
+    /// Пустая таблица без префикса: неизвестные USER_ID не отображаются
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// USER_ID, которых нет в таблице, получают имя `{prefix}{id}`
+    pub fn with_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Задаёт имя счёта для USER_ID
+    pub fn insert(&mut self, user_id: u64, name: impl Into<Name>) -> &mut Self {
+        let name = name.into();
+        self.ids.insert(name.clone(), user_id);
+        if let Some(old) = self.names.insert(user_id, name)
+            && old != self.names[&user_id]
+            && self.ids.get(&old) == Some(&user_id)
+        {
+            self.ids.remove(&old);
+        }
+        self
+    }
+
+    /// USER_ID для имени счёта: из таблицы или из имени вида `{prefix}{id}`
+    pub fn id(&self, name: &str) -> Option<u64> {
+        if let Some(&id) = self.ids.get(name) {
+            return Some(id);
+        }
+        let prefix = self.prefix.as_deref()?;
+        name.strip_prefix(prefix)?.parse().ok()
+    }
+
+    pub fn name(&self, user_id: u64) -> Option<Name> {
+        match (self.names.get(&user_id), &self.prefix) {
+            (Some(name), _) => Some(name.clone()),
+            (None, Some(prefix)) => Some(format!("{}{}", prefix, user_id)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Что делать с записями FAILURE и PENDING
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnsettledPolicy {
+    /// Запись пропускается
+    #[default]
+    Skip,
+
+    /// Операция с тем же статусом добавляется в историю, баланс не меняется
+    Record,
+}
+
+/// Вид ошибки загрузки записи
+#[derive(Debug)]
+pub enum ImportErrorKind {
+    /// Файл не разбирается
+    Parse(Box<ParseError>),
+
+    /// USER_ID нет в [UserMap]
+    UnmappedUser(u64),
+
+    /// Операция не применилась к счёту
+    Manager(BalanceManagerError),
+}
+
+/// Ошибка загрузки записи
+#[derive(Debug)]
+pub struct ImportError {
+    /// Номер записи во входе, с 1; `0` - ошибка до первой записи
+    pub record: u64,
+    pub tx_id: Option<u64>,
+    pub kind: ImportErrorKind,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let ImportErrorKind::Parse(err) = &self.kind {
+            return write!(f, "{}", err);
+        }
+        write!(f, "запись {}", self.record)?;
+        if let Some(tx_id) = self.tx_id {
+            write!(f, " (TX_ID {})", tx_id)?;
+        }
+        match &self.kind {
+            ImportErrorKind::UnmappedUser(user_id) => {
+                write!(f, ": нет счёта для USER_ID {}", user_id)
+            }
+            ImportErrorKind::Manager(err) => write!(f, ": {}", err),
+            ImportErrorKind::Parse(_) => Ok(()),
+        }
+    }
+}
+
+/// Итоги загрузки
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// Записи SUCCESS, изменившие балансы
+    pub applied: u64,
+    /// Записи FAILURE и PENDING, добавленные в историю
+    pub recorded: u64,
+    /// Записи FAILURE и PENDING, пропущенные по политике
+    pub skipped: u64,
+    /// Ошибки записей в нестрогом режиме
+    pub errors: Vec<ImportError>,
+}
+
+/// Применяет записи YPBank к [Storage] через [BalanceManager].
+///
+/// DEPOSIT зачисляется на TO_USER_ID, WITHDRAWAL списывается с FROM_USER_ID,
+/// TRANSFER переводит между ними. Балансы меняют только записи SUCCESS.
+#[derive(Debug, Clone, Default)]
+pub struct Importer {
+    users: UserMap,
+    auto_create: bool,
+    unsettled: UnsettledPolicy,
+    mode: ParseMode,
+}
+
+impl Importer {
+    pub fn new(users: UserMap) -> Self {
+        Self {
+            users,
+            ..Self::default()
+        }
+    }
+
+    /// Создавать счета, которых нет в хранилище
+    pub fn auto_create(mut self, auto_create: bool) -> Self {
+        self.auto_create = auto_create;
+        self
+    }
+
+    pub fn unsettled(mut self, policy: UnsettledPolicy) -> Self {
+        self.unsettled = policy;
+        self
+    }
+
+    /// В нестрогом режиме запись, которую не удалось применить, пропускается,
+    /// а ошибка попадает в отчёт. Ошибка разбора файла прерывает загрузку в любом режиме
+    pub fn mode(mut self, mode: ParseMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Загружает все записи читателя в хранилище
+    pub fn import<R: RecordReader + ?Sized>(
+        &self,
+        storage: &mut Storage,
+        reader: &mut R,
+    ) -> Result<ImportReport, ImportError> {
+        let mut report = ImportReport::default();
+        let mut number = 0;
+        loop {
+            let record = match reader.read_record() {
+                Ok(Some(record)) => record,
+                Ok(None) => return Ok(report),
+                Err(err) => {
+                    return Err(ImportError {
+                        record: number,
+                        tx_id: None,
+                        kind: ImportErrorKind::Parse(Box::new(err)),
+                    });
+                }
+            };
+            number += 1;
+
+            match self.apply(storage, &record, &mut report) {
+                Ok(()) => {}
+                Err(kind) => {
+                    let error = ImportError {
+                        record: number,
+                        tx_id: Some(record.tx_id),
+                        kind,
+                    };
+                    if self.mode == ParseMode::Strict {
+                        return Err(error);
+                    }
+                    report.errors.push(error);
+                }
+            }
+        }
+    }
+
+    fn apply(
+        &self,
+        storage: &mut Storage,
+        record: &TxRecord,
+        report: &mut ImportReport,
+    ) -> Result<(), ImportErrorKind> {
+        let status = match record.status {
+            TxStatus::Success => OperationStatus::SUCCESS,
+            TxStatus::Failure => OperationStatus::FAILURE,
+            TxStatus::Pending => OperationStatus::PENDING,
+        };
+        if status != OperationStatus::SUCCESS && self.unsettled == UnsettledPolicy::Skip {
+            report.skipped += 1;
+            return Ok(());
+        }
+
+        // В bin списания могут храниться отрицательными
+        let amount = record.amount.unsigned_abs();
+        let from = match record.tx_type {
+            TxType::Deposit => None,
+            TxType::Transfer | TxType::Withdrawal => {
+                Some(self.account(storage, record.from_user_id)?)
+            }
+        };
+        let to = match record.tx_type {
+            TxType::Withdrawal => None,
+            TxType::Transfer | TxType::Deposit => Some(self.account(storage, record.to_user_id)?),
+        };
+
+        if status != OperationStatus::SUCCESS {
+            let id = storage._get_id_balance();
+            let ops = match (from, to) {
+                (Some(from), Some(to)) => vec![
+                    (
+                        from.clone(),
+                        Operation::transfer(id, to.clone(), amount, false),
+                    ),
+                    (to, Operation::transfer(id, from, amount, true)),
+                ],
+                (Some(from), None) => vec![(from, Operation::withdraw(id, amount))],
+                (None, Some(to)) => vec![(to, Operation::deposit(id, amount))],
+                (None, None) => vec![],
+            };
+            // Без auto_create счёта может не быть: тогда запись не попадает ни в одну историю
+            if let Some((name, _)) = ops
+                .iter()
+                .find(|(name, _)| !storage.accounts.contains_key(name))
+            {
+                return Err(ImportErrorKind::Manager(BalanceManagerError::UserNotFound(
+                    name.clone(),
+                )));
+            }
+            for (name, mut op) in ops {
+                op.set_status(status.clone());
+                if let Some(balance) = storage.accounts.get_mut(&name) {
+                    balance.record(op);
+                }
+            }
+            report.recorded += 1;
+            return Ok(());
+        }
+
+        let result = match (&from, &to) {
+            (Some(from), Some(to)) => storage.transfer(from, to, amount),
+            (Some(from), None) => storage.withdraw(from, amount),
+            (None, Some(to)) => storage.deposit(to, amount),
+            (None, None) => Ok(()),
+        };
+        result.map_err(ImportErrorKind::Manager)?;
+        report.applied += 1;
+        Ok(())
+    }
+
+    /// Имя счёта для USER_ID, при `auto_create` счёт создаётся
+    fn account(&self, storage: &mut Storage, user_id: u64) -> Result<Name, ImportErrorKind> {
+        let name = self
+            .users
+            .name(user_id)
+            .ok_or(ImportErrorKind::UnmappedUser(user_id))?;
+        if self.auto_create {
+            storage.add_user(name.clone());
+        }
+        Ok(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_matches::assert_matches;
+    use parsers::formats::csv::CsvReader;
+    use parsers::record::testing::record;
+
+    const EXAMPLE: &[u8] = include_bytes!("../../../data/records_example.csv");
+
+    fn value(storage: &Storage, name: &str) -> i128 {
+        storage.get_balance(&name.to_string()).unwrap().get_value()
+    }
+
+    #[test]
+    fn test_user_map_ids() {
+        let mut users = UserMap::new().with_prefix("user_");
+        users.insert(1, "Ivan").insert(2, "Julia").insert(1, "Petr");
+        assert_eq!(users.id("Petr"), Some(1));
+        assert_eq!(users.id("Julia"), Some(2));
+        assert_eq!(users.id("Ivan"), None);
+        assert_eq!(users.id("user_7"), Some(7));
+        assert_eq!(users.name(1), Some("Petr".to_string()));
+    }
+
+    #[test]
+    fn test_import_records() {
+        let mut users = UserMap::new();
+        users.insert(1, "Ivan").insert(2, "Julia");
+        let mut pending = record(4, TxType::Deposit, 0, 2, 1000);
+        pending.status = TxStatus::Pending;
+        let records = vec![
+            record(1, TxType::Deposit, 0, 1, 500),
+            record(2, TxType::Transfer, 1, 2, 200),
+            record(3, TxType::Withdrawal, 2, 0, -50),
+            pending,
+        ];
+
+        let mut storage = Storage::new();
+        let report = Importer::new(users)
+            .auto_create(true)
+            .unsettled(UnsettledPolicy::Record)
+            .import(&mut storage, &mut records.into_iter())
+            .unwrap();
+
+        assert_eq!(report.applied, 3);
+        assert_eq!(report.recorded, 1);
+        assert_eq!(value(&storage, "Ivan"), 300);
+        assert_eq!(value(&storage, "Julia"), 150);
+        let julia = storage.get_balance(&"Julia".to_string()).unwrap();
+        assert_eq!(julia.get_history().len(), 3);
+        assert_eq!(
+            julia.get_history().last().unwrap().status,
+            OperationStatus::PENDING
+        );
+    }
+
+    #[test]
+    fn test_import_errors() {
+        let records = vec![
+            record(1, TxType::Deposit, 0, 7, 100),
+            record(2, TxType::Withdrawal, 7, 0, 500),
+        ];
+
+        let mut storage = Storage::new();
+        let err = Importer::new(UserMap::new())
+            .import(&mut storage, &mut records.clone().into_iter())
+            .unwrap_err();
+        assert_matches!(err.kind, ImportErrorKind::UnmappedUser(7));
+
+        let err = Importer::new(UserMap::new().with_prefix("user_"))
+            .import(&mut storage, &mut records.clone().into_iter())
+            .unwrap_err();
+        assert_matches!(
+            err.kind,
+            ImportErrorKind::Manager(BalanceManagerError::UserNotFound(_))
+        );
+
+        let report = Importer::new(UserMap::new().with_prefix("user_"))
+            .auto_create(true)
+            .mode(ParseMode::Lenient)
+            .import(&mut storage, &mut records.into_iter())
+            .unwrap();
+        assert_eq!(report.applied, 1);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].record, 2);
+        assert_eq!(value(&storage, "user_7"), 100);
+    }
+
+    #[test]
+    fn test_import_unsettled_missing_account() {
+        let mut users = UserMap::new();
+        users.insert(1, "Ivan").insert(2, "Julia");
+        let mut pending = record(1, TxType::Transfer, 1, 2, 100);
+        pending.status = TxStatus::Pending;
+
+        let mut storage = Storage::new();
+        storage.add_user("Ivan".to_string());
+        let importer = Importer::new(users)
+            .auto_create(false)
+            .unsettled(UnsettledPolicy::Record);
+        let err = importer
+            .import(&mut storage, &mut vec![pending.clone()].into_iter())
+            .unwrap_err();
+        assert_matches!(
+            err.kind,
+            ImportErrorKind::Manager(BalanceManagerError::UserNotFound(ref name)) if name == "Julia"
+        );
+        // Операция не записана и в историю существующего счёта
+        let ivan = storage.get_balance(&"Ivan".to_string()).unwrap();
+        assert!(ivan.get_history().is_empty());
+
+        let report = importer
+            .mode(ParseMode::Lenient)
+            .import(&mut storage, &mut vec![pending].into_iter())
+            .unwrap();
+        assert_eq!(report.recorded, 0);
+        assert_eq!(report.errors.len(), 1);
+    }
+
+    #[test]
+    fn test_import_example() {
+        let mut storage = Storage::new();
+        let report = Importer::new(UserMap::new().with_prefix("user_"))
+            .auto_create(true)
+            .mode(ParseMode::Lenient)
+            .import(&mut storage, &mut CsvReader::new(EXAMPLE))
+            .unwrap();
+
+        assert_eq!(report.skipped, 667);
+        assert_eq!(report.applied + report.errors.len() as u64, 333);
+        assert!(storage.get_all().len() > 1);
+    }
+}
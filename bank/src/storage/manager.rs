@@ -3,7 +3,7 @@ use crate::{
     Name,
     balance::{
         manager::{BalanceManager, BalanceManagerError},
-        operations::{Operation, OperationAmount},
+        operations::{Operation, OperationAmount, OperationError},
     },
 };
 
@@ -16,7 +16,7 @@ impl BalanceManager for Storage {
 
         Operation::deposit(id, amount)
             .apply(balance)
-            .map_err(|op_err| BalanceManagerError::OperationError(op_err))?;
+            .map_err(BalanceManagerError::OperationError)?;
 
         Ok(())
     }
@@ -33,7 +33,7 @@ impl BalanceManager for Storage {
 
         Operation::withdraw(id, amount)
             .apply(balance)
-            .map_err(|op_err| BalanceManagerError::OperationError(op_err))?;
+            .map_err(BalanceManagerError::OperationError)?;
 
         Ok(())
     }
@@ -44,16 +44,21 @@ impl BalanceManager for Storage {
         to: &Name,
         amount: OperationAmount,
     ) -> Result<(), BalanceManagerError> {
+        if from == to {
+            return Err(BalanceManagerError::OperationError(
+                OperationError::InvalidOperation(format!("перевод самому себе: {}", from)),
+            ));
+        }
         let id = self._get_id_balance();
         if let [Some(balance_from), Some(balance_to)] = self.accounts.get_disjoint_mut([from, to]) {
             let operation_from = Operation::transfer(id, to.clone(), amount, false);
             let operation_to = Operation::transfer(id, from.clone(), amount, true);
             operation_from
                 .apply(balance_from)
-                .map_err(|op_err| BalanceManagerError::OperationError(op_err))?;
+                .map_err(BalanceManagerError::OperationError)?;
             operation_to
                 .apply(balance_to)
-                .map_err(|op_err| BalanceManagerError::OperationError(op_err))?;
+                .map_err(BalanceManagerError::OperationError)?;
 
             Ok(())
         } else {
@@ -85,6 +90,17 @@ mod tests {
         assert!(res.is_none()); // второй раз — не найден
     }
 
+    #[test]
+    fn test_transfer_to_self() {
+        let mut storage = Storage::new();
+        storage.add_user("Bob".to_string());
+        storage.deposit(&"Bob".to_string(), 100).unwrap();
+
+        let bob = "Bob".to_string();
+        assert!(storage.transfer(&bob, &bob, 50).is_err());
+        assert_eq!(storage.get_balance(&bob).unwrap().get_value(), 100);
+    }
+
     #[test]
     fn test_nonexistent_user() {
         let mut storage = Storage::new();
@@ -1,4 +1,6 @@
+pub mod export;
 pub mod files;
+pub mod import;
 pub mod manager;
 pub mod storage;
 use crate::{Name, balance::Balance};
@@ -0,0 +1,10 @@
+#![no_main]
+
+use bank::balance::{Balance, operations::OperationType};
+use libfuzzer_sys::fuzz_target;
+use parsers::ParseMode;
+
+fuzz_target!(|text: &str| {
+    let _ = OperationType::try_from(text.to_string());
+    let _ = Balance::parse(text, ParseMode::Lenient);
+});
@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use parsers::{Format, ParseMode, RecordReader};
+
+fuzz_target!(|data: &[u8]| {
+    for mode in [ParseMode::Strict, ParseMode::Lenient] {
+        let _ = Format::Text.reader_with(data, mode).read_report();
+    }
+});
@@ -0,0 +1,47 @@
+//! Последовательный и параллельный разбор большого YPBankText:
+//! `cargo bench -p parsers --bench text_parallel`
+
+use criterion::{Criterion, Throughput, criterion_group, criterion_main};
+use parsers::{
+    RecordReader,
+    formats::{
+        parallel::ParTextReader,
+        text::{TextReader, TextWriter},
+    },
+    generate::Generator,
+};
+use std::hint::black_box;
+
+const RECORDS: u64 = 200_000;
+
+/// Файл YPBankText из `count` записей, около 200 байт на запись
+fn generate(count: u64) -> Vec<u8> {
+    let mut writer = TextWriter::new(Vec::new());
+    Generator::new(0)
+        .users(1000)
+        .write(count, &mut writer)
+        .unwrap();
+    writer.into_inner().unwrap()
+}
+
+fn bench_text(c: &mut Criterion) {
+    let data = generate(RECORDS);
+    let mut group = c.benchmark_group("text");
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.sample_size(10);
+
+    group.bench_function("sequential", |b| {
+        b.iter(|| {
+            TextReader::new(black_box(data.as_slice()))
+                .read_all()
+                .unwrap()
+        })
+    });
+    group.bench_function("parallel", |b| {
+        b.iter(|| ParTextReader::new(black_box(&data)).read_all().unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_text);
+criterion_main!(benches);
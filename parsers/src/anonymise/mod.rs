@@ -0,0 +1,335 @@
+//! Детерминированная анонимизация записей для тестовых данных
+//!
+//! FROM_USER_ID и TO_USER_ID заменяются псевдонимами: перестановкой всех ненулевых `u64`,
+//! заданной секретным ключом. Один и тот же ID при одном ключе всегда получает один
+//! и тот же псевдоним, разные ID - разные, поэтому граф переводов сохраняется.
+//! Внешний счёт 0 остаётся нулём. Без ключа псевдоним не обратить.
+//!
+//! DESCRIPTION сохраняется, очищается или заменяется шаблоном. AMOUNT и TIMESTAMP
+//! можно сдвинуть на случайную величину в заданных пределах. Сдвиг зависит только
+//! от ключа и TX_ID, так что одна транзакция в разных выгрузках сдвигается одинаково.
+//!
+//! Результат проходит те же правила [validate](crate::validate), что и исходные данные:
+//! TX_ID и тип не меняются, знак и ненулевость суммы сохраняются, а время, лежавшее
+//! в [TimestampRange], в нём и остаётся.
+
+use crate::{
+    errors::ParseError,
+    formats::{RecordReader, RecordWriter},
+    record::TxRecord,
+    validate::TimestampRange,
+};
+use siphasher::{sip::SipHasher24, sip128};
+use std::{fmt::Display, ops::RangeInclusive, str::FromStr};
+
+/// Наибольший сдвиг AMOUNT в процентах: при нём сумма не меняет знак и не становится нулём
+pub const MAX_AMOUNT_JITTER: u8 = 99;
+
+/// Раундов сети Фейстеля в перестановке ID
+const ROUNDS: u8 = 4;
+
+/// Метки входа псевдослучайной функции, раунды перестановки - `0..ROUNDS`
+const TAG_AMOUNT: u8 = 0x10;
+const TAG_TIMESTAMP: u8 = 0x11;
+
+/// Что делать с DESCRIPTION
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Description {
+    Keep,
+    /// Пустое описание
+    #[default]
+    Clear,
+    /// Шаблон с подстановками `{tx_id}`, `{type}` и `{status}`
+    Template(String),
+}
+
+impl Description {
+    fn apply(&self, record: &TxRecord) -> String {
+        match self {
+            Description::Keep => record.description.clone(),
+            Description::Clear => String::new(),
+            Description::Template(template) => template
+                .replace("{tx_id}", &record.tx_id.to_string())
+                .replace("{type}", &record.tx_type.to_string())
+                .replace("{status}", &record.status.to_string()),
+        }
+    }
+}
+
+impl Display for Description {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Description::Keep => write!(f, "keep"),
+            Description::Clear => write!(f, "clear"),
+            Description::Template(template) => write!(f, "{}", template),
+        }
+    }
+}
+
+/// `keep`, `clear`, иначе шаблон
+impl FromStr for Description {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Ok(match value {
+            "keep" => Description::Keep,
+            "clear" => Description::Clear,
+            template => Description::Template(template.to_string()),
+        })
+    }
+}
+
+/// Анонимизатор записей
+///
+/// ```
+/// use parsers::anonymise::{Anonymiser, Description};
+///
+/// let anonymiser = Anonymiser::new(b"secret")
+///     .description(Description::Template("Transfer {tx_id}".to_string()))
+///     .amount_jitter(10);
+/// assert_eq!(anonymiser.user_id(0), 0);
+/// assert_eq!(anonymiser.user_id(42), Anonymiser::new(b"secret").user_id(42));
+/// assert_ne!(anonymiser.user_id(42), Anonymiser::new(b"other").user_id(42));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Anonymiser {
+    hasher: SipHasher24,
+    description: Description,
+    amount_jitter: u8,
+    time_jitter: u64,
+    valid_time: RangeInclusive<u64>,
+}
+
+impl Anonymiser {
+    /// Ключ `secret` любой длины. Псевдонимы при разных ключах не связаны
+    pub fn new(secret: &[u8]) -> Self {
+        let key = sip128::SipHasher24::new_with_keys(0, 0).hash(secret);
+        let valid_time = TimestampRange::default();
+        Self {
+            hasher: SipHasher24::new_with_keys(key.h1, key.h2),
+            description: Description::default(),
+            amount_jitter: 0,
+            time_jitter: 0,
+            valid_time: valid_time.min..=valid_time.max,
+        }
+    }
+
+    /// Обработка DESCRIPTION, по умолчанию [Description::Clear]
+    pub fn description(mut self, description: Description) -> Self {
+        self.description = description;
+        self
+    }
+
+    /// Сдвиг AMOUNT не больше `percent` процентов суммы, не больше [MAX_AMOUNT_JITTER]
+    pub fn amount_jitter(mut self, percent: u8) -> Self {
+        self.amount_jitter = percent.min(MAX_AMOUNT_JITTER);
+        self
+    }
+
+    /// Сдвиг TIMESTAMP не больше `millis` в обе стороны
+    pub fn time_jitter(mut self, millis: u64) -> Self {
+        self.time_jitter = millis;
+        self
+    }
+
+    /// Псевдоним ID пользователя
+    pub fn user_id(&self, id: u64) -> u64 {
+        if id == 0 {
+            return 0;
+        }
+        // Перестановка всех u64 может отправить ID в 0. Тогда она применяется ещё раз,
+        // пока не выйдет из нуля: так получается перестановка ненулевых значений
+        let mut pseudonym = self.permute(id);
+        while pseudonym == 0 {
+            pseudonym = self.permute(pseudonym);
+        }
+        pseudonym
+    }
+
+    /// Анонимизированная копия записи
+    pub fn record(&self, record: &TxRecord) -> TxRecord {
+        TxRecord {
+            tx_id: record.tx_id,
+            tx_type: record.tx_type,
+            from_user_id: self.user_id(record.from_user_id),
+            to_user_id: self.user_id(record.to_user_id),
+            amount: self.amount(record),
+            timestamp: self.timestamp(record),
+            status: record.status,
+            description: self.description.apply(record),
+        }
+    }
+
+    /// Переливает записи из `reader` в `writer` с анонимизацией, возвращает их количество
+    pub fn anonymise<R, W>(&self, reader: &mut R, writer: &mut W) -> Result<u64, ParseError>
+    where
+        R: RecordReader + ?Sized,
+        W: RecordWriter + ?Sized,
+    {
+        let mut count = 0;
+        while let Some(record) = reader.read_record()? {
+            writer.write_record(&self.record(&record))?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Псевдослучайная функция ключа от метки и значения
+    fn prf(&self, tag: u8, value: u64) -> u64 {
+        let mut input = [tag; 9];
+        input[1..].copy_from_slice(&value.to_be_bytes());
+        self.hasher.hash(&input)
+    }
+
+    /// Сеть Фейстеля на половинах по 32 бита - перестановка всех u64
+    fn permute(&self, value: u64) -> u64 {
+        let (mut left, mut right) = ((value >> 32) as u32, value as u32);
+        for round in 0..ROUNDS {
+            let mixed = left ^ self.prf(round, right.into()) as u32;
+            (left, right) = (right, mixed);
+        }
+        (u64::from(left) << 32) | u64::from(right)
+    }
+
+    /// Сдвиг в пределах `-bound..=bound`
+    fn offset(&self, tag: u8, tx_id: u64, bound: u64) -> i128 {
+        let span = 2 * u128::from(bound) + 1;
+        (u128::from(self.prf(tag, tx_id)) % span) as i128 - i128::from(bound)
+    }
+
+    fn amount(&self, record: &TxRecord) -> i64 {
+        let bound = u128::from(record.amount.unsigned_abs()) * u128::from(self.amount_jitter) / 100;
+        let offset = self.offset(TAG_AMOUNT, record.tx_id, bound as u64);
+        // |offset| < |amount|: знак сохраняется. Сдвиг за пределы i64 обрезается
+        (i128::from(record.amount) + offset).clamp(i64::MIN.into(), i64::MAX.into()) as i64
+    }
+
+    fn timestamp(&self, record: &TxRecord) -> u64 {
+        let offset = self.offset(TAG_TIMESTAMP, record.tx_id, self.time_jitter);
+        let shifted = (i128::from(record.timestamp) + offset).clamp(0, u64::MAX.into()) as u64;
+        if self.valid_time.contains(&record.timestamp) {
+            shifted.clamp(*self.valid_time.start(), *self.valid_time.end())
+        } else {
+            shifted
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Format, TxType,
+        formats::{binary::BinReader, json::JsonWriter},
+        validate::Validator,
+    };
+    use proptest::prelude::*;
+    use std::collections::HashMap;
+
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    #[test]
+    fn test_anonymised_records_stay_valid() {
+        let mut records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        // В примере суммы списаний положительные, для bin им нужен минус
+        for record in &mut records {
+            if record.tx_type == TxType::Withdrawal {
+                record.amount = -record.amount;
+            }
+        }
+        let anonymiser = Anonymiser::new(b"fixtures")
+            .description(Description::Template("{type} {tx_id}".to_string()))
+            .amount_jitter(MAX_AMOUNT_JITTER)
+            .time_jitter(30 * 24 * 60 * 60 * 1000);
+        let anonymised: Vec<_> = records.iter().map(|r| anonymiser.record(r)).collect();
+
+        let mut validator = Validator::standard(Format::Bin);
+        for record in &anonymised {
+            assert_eq!(validator.check(record), vec![]);
+        }
+
+        // Граф переводов тот же: псевдонимы согласованы и не склеивают пользователей
+        let mut pseudonyms = HashMap::new();
+        let mut originals = HashMap::new();
+        for (record, anonymous) in records.iter().zip(&anonymised) {
+            for (id, pseudonym) in [
+                (record.from_user_id, anonymous.from_user_id),
+                (record.to_user_id, anonymous.to_user_id),
+            ] {
+                assert_eq!(*pseudonyms.entry(id).or_insert(pseudonym), pseudonym);
+                assert_eq!(*originals.entry(pseudonym).or_insert(id), id);
+            }
+            assert_eq!(anonymous.tx_id, record.tx_id);
+            assert_eq!(anonymous.status, record.status);
+            assert_eq!(
+                anonymous.description,
+                format!("{} {}", record.tx_type, record.tx_id)
+            );
+        }
+        assert!(pseudonyms.iter().any(|(id, pseudonym)| id != pseudonym));
+        assert!(
+            anonymised
+                .iter()
+                .zip(&records)
+                .any(|(a, r)| a.amount != r.amount)
+        );
+    }
+
+    #[test]
+    fn test_anonymise_stream() {
+        let anonymiser = Anonymiser::new(b"key");
+        let mut writer = JsonWriter::new(Vec::new());
+        let count = anonymiser
+            .anonymise(&mut BinReader::new(EXAMPLE_BIN), &mut writer)
+            .unwrap();
+        assert_eq!(count, 1000);
+
+        let written = writer.into_inner().unwrap();
+        let parsed = Format::Json.reader(written.as_slice()).read_all().unwrap();
+        let records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        for (anonymous, record) in parsed.iter().zip(&records) {
+            // Без сдвигов меняются только пользователи и описание
+            assert_eq!(anonymous.amount, record.amount);
+            assert_eq!(anonymous.timestamp, record.timestamp);
+            assert_eq!(anonymous.description, "");
+        }
+    }
+
+    #[test]
+    fn test_description_from_str() {
+        assert_eq!("keep".parse(), Ok(Description::Keep));
+        assert_eq!("clear".parse(), Ok(Description::Clear));
+        assert_eq!(
+            "Record {tx_id}".parse(),
+            Ok(Description::Template("Record {tx_id}".to_string()))
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn prop_user_id_is_injective(a in any::<u64>(), b in any::<u64>()) {
+            let anonymiser = Anonymiser::new(b"prop");
+            prop_assert_eq!(anonymiser.user_id(a) == anonymiser.user_id(b), a == b);
+            prop_assert_eq!(anonymiser.user_id(a) == 0, a == 0);
+        }
+
+        #[test]
+        fn prop_amount_keeps_sign(amount in any::<i64>(), tx_id in any::<u64>(), percent in 0u8..=255) {
+            let anonymiser = Anonymiser::new(b"prop").amount_jitter(percent);
+            let record = TxRecord {
+                tx_id,
+                tx_type: TxType::Transfer,
+                from_user_id: 1,
+                to_user_id: 2,
+                amount,
+                timestamp: 1633036860000,
+                status: crate::TxStatus::Success,
+                description: String::new(),
+            };
+            let jittered = anonymiser.record(&record).amount;
+            prop_assert_eq!(jittered.signum(), amount.signum());
+            let bound = u128::from(amount.unsigned_abs()) * u128::from(percent.min(MAX_AMOUNT_JITTER)) / 100;
+            prop_assert!(u128::from(jittered.abs_diff(amount)) <= bound);
+        }
+    }
+}
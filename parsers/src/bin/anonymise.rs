@@ -0,0 +1,83 @@
+use parsers::{
+    Format, RecordReader, RecordWriter,
+    anonymise::{Anonymiser, Description, MAX_AMOUNT_JITTER},
+    cli::{
+        Args, CliError, create_output, finish_output, open_input, open_writer, report_skipped,
+        resolve_format,
+    },
+};
+use std::{env, process};
+
+/// Переменная окружения с ключом, если нет --key
+const KEY_VAR: &str = "YPBANK_ANONYMISE_KEY";
+
+const USAGE: &str = "Использование:
+  ypbank-anonymise [--key <секрет>] [--in <файл>] [--from <auto|csv|text|bin|json>]
+                   [--out <файл>] [--to <csv|text|bin|json>] [--description <keep|clear|шаблон>]
+                   [--amount-jitter <процент>] [--time-jitter <мс>] [--lenient] [--bin-version <1|2>]
+
+Заменяет FROM_USER_ID и TO_USER_ID псевдонимами, зависящими от ключа: при том же ключе
+тот же ID получает тот же псевдоним, граф переводов сохраняется. 0 остаётся нулём.
+Без --key ключ берётся из переменной YPBANK_ANONYMISE_KEY.
+--description: keep - оставить, clear - очистить (по умолчанию), иначе шаблон
+с подстановками {tx_id}, {type} и {status}, например 'Record {tx_id}'.
+--amount-jitter - сдвиг AMOUNT до заданного процента суммы, 0..99.
+--time-jitter - сдвиг TIMESTAMP до заданного числа миллисекунд в обе стороны.
+Без --to формат выхода - по расширению --out, иначе формат входа.";
+
+fn run(args: &Args) -> Result<u64, CliError> {
+    let key = match args.get("key") {
+        Some(key) => key.to_string(),
+        None => {
+            env::var(KEY_VAR).map_err(|_| CliError::Usage("не указан ключ --key".to_string()))?
+        }
+    };
+    let mut anonymiser = Anonymiser::new(key.as_bytes());
+    if let Some(description) = args.value::<Description>("description")? {
+        anonymiser = anonymiser.description(description);
+    }
+    if let Some(percent) = args.value::<u8>("amount-jitter")? {
+        if percent > MAX_AMOUNT_JITTER {
+            return Err(CliError::Usage(format!(
+                "--amount-jitter больше {}",
+                MAX_AMOUNT_JITTER
+            )));
+        }
+        anonymiser = anonymiser.amount_jitter(percent);
+    }
+    if let Some(millis) = args.value::<u64>("time-jitter")? {
+        anonymiser = anonymiser.time_jitter(millis);
+    }
+
+    let mut input = open_input(args.get("in"))?;
+    let from = resolve_format(&mut input, args.input_format("from")?)?;
+    let to = match args.get("to") {
+        Some(_) => args.format("to")?,
+        None => args.get("out").and_then(Format::from_path).unwrap_or(from),
+    };
+    let mut reader = from.reader_with(input, args.mode());
+    let mut output = create_output(args.get("out"))?;
+    let mut writer = open_writer(Box::new(&mut output), to, args.bin_version()?);
+    let count = anonymiser.anonymise(&mut reader, &mut writer)?;
+    writer.finish()?;
+    drop(writer);
+    finish_output(output, args.get("out"))?;
+    report_skipped(args.get("in").unwrap_or("stdin"), &reader.take_errors());
+    Ok(count)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(count) => eprintln!("Анонимизировано записей: {}", count),
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
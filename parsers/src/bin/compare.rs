@@ -0,0 +1,87 @@
+use parsers::{
+    Format, RecordReader,
+    cli::{Args, CliError, open_input, open_reader, report_skipped},
+    compare::{Comparison, compare},
+    record::FIELDS,
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-compare <файл1> <файл2> [--format1 <auto|csv|text|bin|json>] [--format2 <auto|csv|text|bin|json>] [--ignore <ПОЛЕ,ПОЛЕ>] [--lenient]
+
+Формат по умолчанию определяется по расширению файла (.csv, .txt, .bin), иначе по содержимому.
+С --lenient записи с ошибками пропускаются, ошибки выводятся в stderr.
+Код выхода: 0 - записи совпадают, 1 - есть расхождения, 2 - ошибка.";
+
+/// Формат из аргумента или по расширению файла, `None` - по содержимому
+fn file_format(args: &Args, key: &str, path: &str) -> Result<Option<Format>, CliError> {
+    match args.get(key) {
+        Some(_) => args.input_format(key),
+        None => Ok(Format::from_path(path)),
+    }
+}
+
+fn run(args: &Args) -> Result<Comparison, CliError> {
+    let [left, right] = args.positional.as_slice() else {
+        return Err(CliError::Usage("нужно указать два файла".to_string()));
+    };
+    let left_format = file_format(args, "format1", left)?;
+    let right_format = file_format(args, "format2", right)?;
+    let ignored = args.list("ignore", &FIELDS)?;
+
+    let mode = args.mode();
+    let mut left_reader = open_reader(open_input(Some(left))?, left_format, mode)?;
+    let mut right_reader = open_reader(open_input(Some(right))?, right_format, mode)?;
+
+    let result = compare(&mut left_reader, &mut right_reader, &ignored)?;
+    report_skipped(left, &left_reader.take_errors());
+    report_skipped(right, &right_reader.take_errors());
+    Ok(result)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let result = match run(&args) {
+        Ok(result) => result,
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(2);
+        }
+    };
+
+    if result.is_equal() {
+        println!("Записи совпадают");
+        return;
+    }
+    for tx_id in &result.missing {
+        println!("- TX_ID {}: нет во втором файле", tx_id);
+    }
+    for tx_id in &result.extra {
+        println!("+ TX_ID {}: нет в первом файле", tx_id);
+    }
+    for mismatch in &result.mismatches {
+        println!(
+            "~ TX_ID {}: {} {} != {}",
+            mismatch.tx_id, mismatch.field, mismatch.left, mismatch.right
+        );
+    }
+    for tx_id in &result.left_duplicates {
+        println!("! TX_ID {}: повторяется в первом файле", tx_id);
+    }
+    for tx_id in &result.right_duplicates {
+        println!("! TX_ID {}: повторяется во втором файле", tx_id);
+    }
+    println!(
+        "Итого: {} отсутствует, {} лишних, {} расхождений полей, {} повторов",
+        result.missing.len(),
+        result.extra.len(),
+        result.mismatches.len(),
+        result.left_duplicates.len() + result.right_duplicates.len()
+    );
+    process::exit(1);
+}
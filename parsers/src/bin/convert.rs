@@ -0,0 +1,51 @@
+use parsers::{
+    RecordReader, RecordWriter,
+    cli::{
+        Args, CliError, create_output, finish_output, open_input, open_reader, open_writer,
+        report_skipped,
+    },
+    formats::copy_records,
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-convert --to <csv|text|bin|json> [--from <auto|csv|text|bin|json>] [--in <файл>] [--out <файл>] [--lenient]
+                 [--bin-version <1|2>]
+
+Без --in читает stdin, без --out пишет в stdout.
+Без --from или с --from auto формат входа определяется по содержимому.
+С --lenient записи с ошибками пропускаются, ошибки выводятся в stderr.
+--bin-version 2 пишет bin версии 2: заголовок файла и CRC32 каждой записи.
+Читаются обе версии.";
+
+fn run(args: &Args) -> Result<u64, CliError> {
+    let from = args.input_format("from")?;
+    let to = args.format("to")?;
+    let version = args.bin_version()?;
+
+    let mut reader = open_reader(open_input(args.get("in"))?, from, args.mode())?;
+    let mut output = create_output(args.get("out"))?;
+    let mut writer = open_writer(Box::new(&mut output), to, version);
+    let count = copy_records(&mut reader, &mut writer)?;
+    writer.finish()?;
+    drop(writer);
+    finish_output(output, args.get("out"))?;
+    report_skipped(args.get("in").unwrap_or("stdin"), &reader.take_errors());
+    Ok(count)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(count) => eprintln!("Сконвертировано записей: {}", count),
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use parsers::{
+    Format, RecordWriter,
+    cli::{Args, CliError, create_output, finish_output, open_writer},
+    generate::{Generator, time_range},
+};
+use std::{env, process, str::FromStr};
+
+const USAGE: &str = "Использование:
+  ypbank-generate --count <N> [--seed <N>] [--out <файл>] [--to <csv|text|bin|json>]
+                  [--users <N>] [--types <ТИП=ВЕС,...>] [--statuses <СТАТУС=ВЕС,...>]
+                  [--amounts <[log:]ОТ..=ДО>] [--time <ОТ..ДО>] [--first-id <TX_ID>]
+                  [--consistent] [--bin-version <1|2>]
+
+Генерирует --count записей. При том же --seed (по умолчанию 0) и настройках
+получается тот же файл. Без --out пишет в stdout, без --to формат - по расширению --out.
+--users - пользователи 1..=N, по умолчанию 100.
+--types - веса типов, по умолчанию deposit=1,transfer=1,withdrawal=1.
+--statuses - веса статусов, по умолчанию success=8,failure=1,pending=1.
+--amounts - суммы в центах: 100..=5000 равномерно, log:100..=1000000 (по умолчанию) -
+равномерно по логарифму, мелких сумм больше.
+--time - период TIMESTAMP, числа или даты UTC: 2021-03, 2021-03-01..2021-04-15.
+По умолчанию 30 дней с 2021-09-30.
+С --consistent успешные списания и переводы не превышают баланс отправителя.
+В bin суммы списаний отрицательные, в остальных форматах - положительные.
+
+Пример: год операций 10 000 пользователей
+  ypbank-generate --count 1000000 --users 10000 --time 2021-01-01..2022-01-01 --consistent --out year.bin.zst";
+
+/// Значение аргумента через `parse` с подробной ошибкой
+fn parsed<T>(
+    args: &Args,
+    key: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+) -> Result<Option<T>, CliError> {
+    args.get(key)
+        .map(|value| parse(value).map_err(|e| CliError::Usage(format!("--{}: {}", key, e))))
+        .transpose()
+}
+
+fn run(args: &Args) -> Result<u64, CliError> {
+    let count = args
+        .value::<u64>("count")?
+        .ok_or(CliError::Usage("не указан аргумент --count".to_string()))?;
+    let to = match args.get("to") {
+        Some(_) => args.format("to")?,
+        None => args
+            .get("out")
+            .and_then(Format::from_path)
+            .ok_or(CliError::Usage("не указан формат выхода --to".to_string()))?,
+    };
+
+    let mut generator = Generator::new(args.value("seed")?.unwrap_or(0))
+        .consistent(args.flag("consistent"))
+        .signed_amounts(to == Format::Bin);
+    if let Some(users) = args.value("users")? {
+        generator = generator.users(users);
+    }
+    if let Some(types) = parsed(args, "types", FromStr::from_str)? {
+        generator = generator.types(types);
+    }
+    if let Some(statuses) = parsed(args, "statuses", FromStr::from_str)? {
+        generator = generator.statuses(statuses);
+    }
+    if let Some(amounts) = parsed(args, "amounts", FromStr::from_str)? {
+        generator = generator.amounts(amounts);
+    }
+    if let Some((start, end)) = parsed(args, "time", time_range)? {
+        generator = generator.time_range(start, end);
+    }
+    if let Some(tx_id) = args.value("first-id")? {
+        generator = generator.first_tx_id(tx_id);
+    }
+
+    let mut output = create_output(args.get("out"))?;
+    let mut writer = open_writer(Box::new(&mut output), to, args.bin_version()?);
+    generator.write(count, &mut writer)?;
+    writer.finish()?;
+    drop(writer);
+    finish_output(output, args.get("out"))?;
+    Ok(count)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(count) => eprintln!("Сгенерировано записей: {}", count),
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
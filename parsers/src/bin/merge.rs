@@ -0,0 +1,101 @@
+use parsers::{
+    Format, RecordReader,
+    cli::{
+        Args, CliError, create_output, finish_output, open_input, open_reader, open_writer,
+        report_skipped,
+    },
+    merge::{MergeError, MergeReport, Merger},
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-merge <файл>... [--out <файл>] [--to <csv|text|bin|json>] [--memory <МиБ>]
+               [--temp-dir <каталог>] [--max-conflicts <N>] [--lenient] [--bin-version <1|2>]
+
+Сливает файлы в один, отсортированный по TIMESTAMP. Форматы входов могут различаться,
+формат определяется по расширению файла, иначе по содержимому.
+Из записей с одним TX_ID остаётся первая по порядку файлов. Повторы с другими полями -
+конфликты, их различия выводятся в stderr. --max-conflicts - сколько конфликтов
+выводить, по умолчанию 1000, остальные только считаются.
+Без --out пишет в stdout. Без --to формат выхода - по расширению --out.
+--memory - память под записи в МиБ, по умолчанию 256. Остальное сортируется
+во временных файлах в --temp-dir, по умолчанию в системном каталоге.
+С --lenient записи с ошибками пропускаются, ошибки выводятся в stderr.";
+
+fn run(args: &Args) -> Result<MergeReport, CliError> {
+    let paths = &args.positional;
+    if paths.is_empty() {
+        return Err(CliError::Usage("не указаны входные файлы".to_string()));
+    }
+    let to = match args.get("to") {
+        Some(_) => args.format("to")?,
+        None => args
+            .get("out")
+            .and_then(Format::from_path)
+            .ok_or(CliError::Usage("не указан формат выхода --to".to_string()))?,
+    };
+
+    let mut merger = Merger::new();
+    if let Some(megabytes) = args.value::<usize>("memory")? {
+        merger = merger.memory_budget(megabytes << 20);
+    }
+    if let Some(dir) = args.get("temp-dir") {
+        merger = merger.temp_dir(dir);
+    }
+    if let Some(conflicts) = args.value::<usize>("max-conflicts")? {
+        merger = merger.max_conflicts(conflicts);
+    }
+
+    let mode = args.mode();
+    let mut inputs = paths
+        .iter()
+        .map(|path| open_reader(open_input(Some(path))?, Format::from_path(path), mode))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut output = create_output(args.get("out"))?;
+    let mut writer = open_writer(Box::new(&mut output), to, args.bin_version()?);
+
+    let report = merger
+        .merge(&mut inputs, &mut writer)
+        .map_err(|err| match err {
+            MergeError::Input { input, error } => {
+                CliError::Failed(format!("{}: {}", paths[input], error))
+            }
+            err => CliError::Failed(err.to_string()),
+        })?;
+    writer.finish()?;
+    drop(writer);
+    finish_output(output, args.get("out"))?;
+    for (path, reader) in paths.iter().zip(&mut inputs) {
+        report_skipped(path, &reader.take_errors());
+    }
+    Ok(report)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(report) => {
+            for conflict in &report.conflicts {
+                for mismatch in conflict.mismatches() {
+                    eprintln!(
+                        "конфликт TX_ID {}: {} {} != {}",
+                        mismatch.tx_id, mismatch.field, mismatch.left, mismatch.right
+                    );
+                }
+            }
+            eprintln!(
+                "Прочитано записей: {}, записано: {}, повторов: {}, конфликтов: {}",
+                report.read, report.written, report.duplicates, report.conflict_count
+            );
+        }
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,97 @@
+use parsers::{
+    Format, RecordReader, RecordWriter,
+    cli::{
+        Args, CliError, create_output, finish_output, open_input, open_writer, report_skipped,
+        resolve_format,
+    },
+    query::{Query, TERMS},
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-query [--in <файл>] [--from <auto|csv|text|bin|json>] [--out <файл>] [--to <csv|text|bin|json>]
+               [--user <ID>] [--from-user <ID>] [--to-user <ID>]
+               [--type <ТИП,ТИП>] [--status <СТАТУС,СТАТУС>]
+               [--amount <СУММА|ОТ..ДО>] [--time <ВРЕМЯ|ОТ..ДО>] [--desc <REGEX>]
+               [--limit <N>] [--count] [--lenient] [--bin-version <1|2>]
+
+Отбирает записи, подходящие под все условия, и пишет их в формате --to.
+Без --to формат выхода - по расширению --out, иначе формат входа.
+Диапазоны: 100..500 (конец не входит), 100..=500, 100.., ..500.
+Время - миллисекунды или даты UTC: 2021-03 (весь март), 2021-03-08, 2021-03-08T12:00,
+2021-03-01..2021-04-01.
+С --count выводит только количество подходящих записей.
+--bin-version 2 пишет bin версии 2 с CRC32 записей.
+
+Пример: все неуспешные переводы пользователя 42 в марте
+  ypbank-query --in a.bin --type transfer --status failure --user 42 --time 2021-03 --to csv";
+
+/// Количество прочитанных и отобранных записей
+fn run(args: &Args) -> Result<(u64, u64), CliError> {
+    let mut query = Query::new();
+    for term in TERMS {
+        if let Some(value) = args.get(term) {
+            query = query
+                .term(term, value)
+                .map_err(|e| CliError::Usage(e.to_string()))?;
+        }
+    }
+    let limit = args.value::<u64>("limit")?.unwrap_or(u64::MAX);
+
+    let mut input = open_input(args.get("in"))?;
+    let from = resolve_format(&mut input, args.input_format("from")?)?;
+    let to = match args.get("to") {
+        Some(_) => args.format("to")?,
+        None => args.get("out").and_then(Format::from_path).unwrap_or(from),
+    };
+    let version = args.bin_version()?;
+    let mut reader = from.reader_with(input, args.mode());
+    let mut output = match args.flag("count") {
+        true => None,
+        false => Some(create_output(args.get("out"))?),
+    };
+    let mut writer = output
+        .as_mut()
+        .map(|output| open_writer(Box::new(output), to, version));
+
+    let (mut read, mut selected) = (0, 0);
+    while selected < limit {
+        let Some(record) = reader.read_record()? else {
+            break;
+        };
+        read += 1;
+        if !query.matches(&record) {
+            continue;
+        }
+        selected += 1;
+        if let Some(writer) = writer.as_mut() {
+            writer.write_record(&record)?;
+        }
+    }
+    if let Some(writer) = writer.as_mut() {
+        writer.finish()?;
+    }
+    drop(writer);
+    if let Some(output) = output {
+        finish_output(output, args.get("out"))?;
+    }
+    report_skipped(args.get("in").unwrap_or("stdin"), &reader.take_errors());
+    Ok((read, selected))
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok((_, selected)) if args.flag("count") => println!("{}", selected),
+        Ok((read, selected)) => eprintln!("Отобрано записей: {} из {}", selected, read),
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use parsers::{
+    Format, RecordReader,
+    cli::{Args, CliError, open_input, report_skipped, resolve_format},
+    split::{SplitError, SplitKey, SplitReport, Splitter},
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-split --by <user|from-user|to-user|month|type|status> --out <шаблон>
+               [--in <файл>] [--from <auto|csv|text|bin|json>] [--to <csv|text|bin|json>]
+               [--max-open <N>] [--lenient] [--bin-version <1|2>]
+
+Раскладывает записи по файлам частей. Путь части - шаблон --out, где {key} заменяется
+значением ключа. Существующие файлы перезаписываются, каталоги создаются.
+Ключи: user - оба участника, запись попадает в части отправителя и получателя,
+внешний счёт 0 своей части не получает; from-user, to-user - один участник;
+month - месяц TIMESTAMP по UTC, 2021-03; type, status - в нижнем регистре.
+Без --to формат частей - по расширению шаблона, иначе формат входа.
+--max-open - сколько файлов держать открытыми, по умолчанию 128.
+С --lenient записи с ошибками пропускаются, ошибки выводятся в stderr.
+
+Пример: выгрузка каждому пользователю в своём каталоге
+  ypbank-split --in a.bin --by user --out 'teams/{key}/records.csv.gz'";
+
+fn run(args: &Args) -> Result<SplitReport, CliError> {
+    let key = args
+        .required("by")?
+        .parse::<SplitKey>()
+        .map_err(CliError::Usage)?;
+    let template = args.required("out")?;
+
+    let mut input = open_input(args.get("in"))?;
+    let from = resolve_format(&mut input, args.input_format("from")?)?;
+    let to = match args.get("to") {
+        Some(_) => args.format("to")?,
+        None => Format::from_path(template).unwrap_or(from),
+    };
+    let mut splitter = Splitter::new(key, template)
+        .format(to)
+        .bin_version(args.bin_version()?);
+    if let Some(files) = args.value::<usize>("max-open")? {
+        splitter = splitter.max_open(files);
+    }
+
+    let mut reader = from.reader_with(input, args.mode());
+    let report = splitter.split(&mut reader).map_err(|err| match err {
+        SplitError::Template(_) => CliError::Usage(err.to_string()),
+        err => CliError::Failed(err.to_string()),
+    })?;
+    report_skipped(args.get("in").unwrap_or("stdin"), &reader.take_errors());
+    Ok(report)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    match run(&args) {
+        Ok(report) => {
+            for part in &report.parts {
+                println!("{}\t{}", part.path.display(), part.records);
+            }
+            eprintln!(
+                "Прочитано записей: {}, частей: {}",
+                report.read,
+                report.parts.len()
+            );
+        }
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(1);
+        }
+    }
+}
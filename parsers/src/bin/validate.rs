@@ -0,0 +1,64 @@
+use parsers::{
+    Format, RecordReader,
+    cli::{Args, CliError, open_input, report_skipped, resolve_format},
+    validate::{ValidationReport, Validator},
+};
+use std::{env, process};
+
+const USAGE: &str = "Использование:
+  ypbank-validate [<файл>] [--format <auto|csv|text|bin|json>] [--skip <ПРАВИЛО,ПРАВИЛО>] [--lenient]
+
+Без файла или с файлом `-` читает stdin. Формат по умолчанию определяется по расширению, иначе по содержимому.
+Правила: deposit-from, withdrawal-to, unique-id, timestamp, amount-sign (только bin).
+Код выхода: 0 - нарушений нет, 1 - есть нарушения, 2 - ошибка.";
+
+fn run(args: &Args) -> Result<ValidationReport, CliError> {
+    let path = match args.positional.as_slice() {
+        [] => None,
+        [path] => Some(path.as_str()),
+        _ => return Err(CliError::Usage("нужно указать один файл".to_string())),
+    };
+    let format = match args.get("format") {
+        Some(_) => args.input_format("format")?,
+        None => path.and_then(Format::from_path),
+    };
+
+    let mut input = open_input(path)?;
+    let format = resolve_format(&mut input, format)?;
+    let validator = Validator::standard(format);
+    let skipped = args.list("skip", &validator.rule_names())?;
+    let mut validator = validator.without(&skipped);
+    let mut reader = format.reader_with(input, args.mode());
+
+    let report = validator.validate(&mut reader)?;
+    report_skipped(path.unwrap_or("stdin"), &reader.take_errors());
+    Ok(report)
+}
+
+fn main() {
+    let args = Args::parse(env::args().skip(1));
+    if args.flag("help") {
+        println!("{}", USAGE);
+        return;
+    }
+
+    let report = match run(&args) {
+        Ok(report) => report,
+        Err(e) => {
+            e.report(USAGE);
+            process::exit(2);
+        }
+    };
+
+    for violation in &report.violations {
+        println!("{}", violation);
+    }
+    println!(
+        "Проверено записей: {}, нарушений: {}",
+        report.checked,
+        report.violations.len()
+    );
+    if !report.is_valid() {
+        process::exit(1);
+    }
+}
@@ -0,0 +1,307 @@
+//! Общие части консольных утилит крейта
+
+use crate::{
+    compress::{CompressWriter, Compression, decompress},
+    errors::{ParseError, ParseMode},
+    formats::{
+        DetectError, Format, RecordReader, RecordWriter,
+        binary::{BinVersion, BinWriter},
+        detect_format,
+    },
+};
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    mem,
+    str::FromStr,
+};
+
+/// Ошибка утилиты
+#[derive(Debug, PartialEq, Eq)]
+pub enum CliError {
+    /// Неверные аргументы, выводятся вместе со справкой
+    Usage(String),
+
+    /// Ошибка при чтении или записи данных
+    Failed(String),
+}
+
+impl CliError {
+    fn io(path: &str, err: io::Error) -> Self {
+        CliError::Failed(format!("ошибка: {}: {}", path, err))
+    }
+
+    /// Выводит ошибку в stderr, ошибку аргументов - вместе со справкой
+    pub fn report(&self, usage: &str) {
+        eprintln!("{}", self);
+        if let CliError::Usage(_) = self {
+            eprintln!("\n{}", usage);
+        }
+    }
+}
+
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::Usage(message) => write!(f, "ошибка: {}", message),
+            CliError::Failed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+impl From<ParseError> for CliError {
+    fn from(err: ParseError) -> Self {
+        CliError::Failed(err.to_string())
+    }
+}
+
+impl From<DetectError> for CliError {
+    fn from(err: DetectError) -> Self {
+        CliError::Failed(format!("ошибка: {}", err))
+    }
+}
+
+/// Аргументы командной строки вида `--key value`, `--flag` и позиционные.
+/// Значением считается следующий аргумент, если он не начинается с `--`
+#[derive(Debug, Default)]
+pub struct Args {
+    values: HashMap<String, String>,
+    pub positional: Vec<String>,
+}
+
+impl Args {
+    /// Разбирает аргументы без имени программы
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Self {
+        let mut result = Args::default();
+        let mut args = args.into_iter().peekable();
+        while let Some(arg) = args.next() {
+            if let Some(key) = arg.strip_prefix("--") {
+                let value = match args.peek() {
+                    Some(next) if !next.starts_with("--") => args.next().unwrap(),
+                    _ => String::new(),
+                };
+                result.values.insert(key.to_string(), value);
+            } else {
+                result.positional.push(arg);
+            }
+        }
+        result
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(|v| v.as_str())
+    }
+
+    /// Флаг без значения
+    pub fn flag(&self, key: &str) -> bool {
+        self.values.contains_key(key)
+    }
+
+    /// Режим разбора входа: с `--lenient` неверные записи пропускаются
+    pub fn mode(&self) -> ParseMode {
+        if self.flag("lenient") {
+            ParseMode::Lenient
+        } else {
+            ParseMode::Strict
+        }
+    }
+
+    pub fn required(&self, key: &str) -> Result<&str, CliError> {
+        self.get(key)
+            .filter(|v| !v.is_empty())
+            .ok_or(CliError::Usage(format!("не указан аргумент --{}", key)))
+    }
+
+    /// Значение, разобранное через `FromStr`
+    pub fn value<T: FromStr>(&self, key: &str) -> Result<Option<T>, CliError> {
+        match self.get(key) {
+            None => Ok(None),
+            Some(value) => value
+                .parse()
+                .map(Some)
+                .map_err(|_| CliError::Usage(format!("неверное значение --{}: {:?}", key, value))),
+        }
+    }
+
+    /// Список через запятую, каждое значение - одно из `allowed`. Нет аргумента - пустой список
+    pub fn list(&self, key: &str, allowed: &[&str]) -> Result<Vec<&str>, CliError> {
+        let Some(value) = self.get(key) else {
+            return Ok(Vec::new());
+        };
+        value
+            .split(',')
+            .map(str::trim)
+            .map(|item| {
+                if allowed.contains(&item) {
+                    return Ok(item);
+                }
+                Err(CliError::Usage(format!(
+                    "неизвестное значение --{}: {:?}, допустимы: {}",
+                    key,
+                    item,
+                    allowed.join(", ")
+                )))
+            })
+            .collect()
+    }
+
+    /// Версия YPBankBin на выходе, `--bin-version <1|2>`, по умолчанию 1
+    pub fn bin_version(&self) -> Result<BinVersion, CliError> {
+        Ok(self.value("bin-version")?.unwrap_or_default())
+    }
+
+    pub fn format(&self, key: &str) -> Result<Format, CliError> {
+        self.required(key)?.parse().map_err(CliError::Usage)
+    }
+
+    /// Формат входного файла. `None` - аргумента нет или он равен `auto`,
+    /// формат определяется по содержимому
+    pub fn input_format(&self, key: &str) -> Result<Option<Format>, CliError> {
+        match self.get(key) {
+            None | Some("auto") => Ok(None),
+            Some(_) => self.format(key).map(Some),
+        }
+    }
+}
+
+/// Формат входа: заданный или определённый через [detect_format].
+/// После определения `input` читается с начала
+pub fn resolve_format<'a>(
+    input: &mut Box<dyn BufRead + 'a>,
+    format: Option<Format>,
+) -> Result<Format, CliError> {
+    if let Some(format) = format {
+        return Ok(format);
+    }
+    let (format, detected) = detect_format(mem::replace(input, Box::new(io::empty())))?;
+    *input = Box::new(detected);
+    Ok(format)
+}
+
+/// Читатель входа. Без формата он определяется через [detect_format]
+pub fn open_reader<'a>(
+    mut input: Box<dyn BufRead + 'a>,
+    format: Option<Format>,
+    mode: ParseMode,
+) -> Result<Box<dyn RecordReader + 'a>, CliError> {
+    let format = resolve_format(&mut input, format)?;
+    Ok(format.reader_with(input, mode))
+}
+
+/// Писатель выхода в формате `format`, для `bin` - версии `version`
+pub fn open_writer<'a>(
+    output: Box<dyn Write + 'a>,
+    format: Format,
+    version: BinVersion,
+) -> Box<dyn RecordWriter + 'a> {
+    match format {
+        Format::Bin => Box::new(BinWriter::with_version(output, version)),
+        _ => format.writer(output),
+    }
+}
+
+/// Выводит в stderr ошибки записей, пропущенных в нестрогом режиме
+pub fn report_skipped(name: &str, errors: &[ParseError]) {
+    if !errors.is_empty() {
+        eprintln!("{}: пропущено записей с ошибками: {}", name, errors.len());
+    }
+    for err in errors {
+        eprintln!("{}\n", err);
+    }
+}
+
+/// Открывает файл на чтение, `None` или `-` - stdin. Сжатый gzip или zstd вход распаковывается
+pub fn open_input(path: Option<&str>) -> Result<Box<dyn BufRead>, CliError> {
+    let path = path.unwrap_or("-");
+    let input: Box<dyn BufRead> = match path {
+        "-" => Box::new(BufReader::new(io::stdin())),
+        path => Box::new(BufReader::new(
+            File::open(path).map_err(|e| CliError::io(path, e))?,
+        )),
+    };
+    decompress(input).map_err(|e| CliError::io(path, e))
+}
+
+/// Выход команды: файл или stdout
+pub type Output = CompressWriter<Box<dyn Write>>;
+
+/// Создаёт файл на запись, `None` или `-` - stdout. Пути `.gz` и `.zst` сжимаются
+pub fn create_output(path: Option<&str>) -> Result<Output, CliError> {
+    match path {
+        None | Some("-") => Ok(CompressWriter::new(
+            Box::new(BufWriter::new(io::stdout())),
+            Compression::None,
+        )),
+        Some(path) => {
+            let file = File::create(path).map_err(|e| CliError::io(path, e))?;
+            Ok(CompressWriter::new(
+                Box::new(BufWriter::new(file)),
+                Compression::from_path(path),
+            ))
+        }
+    }
+}
+
+/// Завершает выход после [RecordWriter::finish]: дописывает сжатый кадр
+pub fn finish_output(output: Output, path: Option<&str>) -> Result<(), CliError> {
+    output
+        .into_inner()
+        .map(drop)
+        .map_err(|e| CliError::io(path.unwrap_or("stdout"), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_args_parse() {
+        let args = Args::parse(
+            ["b.csv", "--in", "a.bin", "--from", "bin", "--resync"]
+                .iter()
+                .map(|a| a.to_string()),
+        );
+
+        assert_eq!(args.get("in"), Some("a.bin"));
+        assert_eq!(args.format("from"), Ok(Format::Bin));
+        assert!(args.flag("resync"));
+        assert!(args.required("resync").is_err());
+        assert!(args.required("to").is_err());
+        assert_eq!(args.positional, vec!["b.csv".to_string()]);
+        assert_eq!(args.input_format("from"), Ok(Some(Format::Bin)));
+        assert_eq!(
+            args.input_format("in"),
+            Err(CliError::Usage("неизвестный формат: a.bin".into()))
+        );
+        assert!(args.value::<u64>("from").is_err());
+        assert_eq!(args.value::<u64>("limit"), Ok(None));
+        assert_eq!(args.input_format("format"), Ok(None));
+        assert_eq!(args.bin_version(), Ok(BinVersion::V1));
+
+        let args = Args::parse(["--bin-version", "2"].iter().map(|a| a.to_string()));
+        assert_eq!(args.bin_version(), Ok(BinVersion::V2));
+
+        let args =
+            Args::parse(["--ignore", "AMOUNT , STATUS", "--skip", "AMOUNT,x"].map(String::from));
+        let allowed = ["AMOUNT", "STATUS"];
+        assert_eq!(args.list("ignore", &allowed), Ok(vec!["AMOUNT", "STATUS"]));
+        assert_eq!(args.list("none", &allowed), Ok(vec![]));
+        assert_eq!(
+            args.list("skip", &allowed),
+            Err(CliError::Usage(
+                "неизвестное значение --skip: \"x\", допустимы: AMOUNT, STATUS".into()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_open_reader_auto() {
+        let input = include_bytes!("../../data/records_example.bin");
+        let mut reader = open_reader(Box::new(&input[..]), None, ParseMode::Strict).unwrap();
+        assert_eq!(reader.read_all().unwrap().len(), 1000);
+
+        assert!(open_reader(Box::new("garbage".as_bytes()), None, ParseMode::Strict).is_err());
+    }
+}
@@ -0,0 +1,194 @@
+//! Сравнение двух наборов записей, возможно разных форматов
+
+use crate::{errors::ParseError, formats::RecordReader, record::TxRecord};
+use std::collections::{HashMap, HashSet, hash_map::Entry};
+
+/// Расхождение значения поля у записей с одним TX_ID
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldMismatch {
+    pub tx_id: u64,
+    pub field: &'static str,
+    pub left: String,
+    pub right: String,
+}
+
+/// Результат сравнения
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Comparison {
+    /// TX_ID, которые есть только в первом наборе
+    pub missing: Vec<u64>,
+    /// TX_ID, которые есть только во втором наборе
+    pub extra: Vec<u64>,
+    pub mismatches: Vec<FieldMismatch>,
+    /// TX_ID, которые повторяются в первом наборе. Сравнивается первая запись
+    pub left_duplicates: Vec<u64>,
+    /// TX_ID, которые повторяются во втором наборе. Сравнивается первая запись
+    pub right_duplicates: Vec<u64>,
+}
+
+impl Comparison {
+    pub fn is_equal(&self) -> bool {
+        self.missing.is_empty()
+            && self.extra.is_empty()
+            && self.mismatches.is_empty()
+            && self.left_duplicates.is_empty()
+            && self.right_duplicates.is_empty()
+    }
+}
+
+/// Значения полей записи в текстовом виде, в порядке спецификации
+pub(crate) fn field_values(record: &TxRecord) -> [(&'static str, String); 8] {
+    [
+        ("TX_ID", record.tx_id.to_string()),
+        ("TX_TYPE", record.tx_type.to_string()),
+        ("FROM_USER_ID", record.from_user_id.to_string()),
+        ("TO_USER_ID", record.to_user_id.to_string()),
+        ("AMOUNT", record.amount.to_string()),
+        ("TIMESTAMP", record.timestamp.to_string()),
+        ("STATUS", record.status.to_string()),
+        ("DESCRIPTION", record.description.clone()),
+    ]
+}
+
+/// Сравнивает записи по TX_ID. Поля из `ignored` не сравниваются.
+///
+/// Первый набор загружается в память, второй читается потоком, от него хранятся только TX_ID.
+pub fn compare<L, R>(
+    left: &mut L,
+    right: &mut R,
+    ignored: &[&str],
+) -> Result<Comparison, ParseError>
+where
+    L: RecordReader + ?Sized,
+    R: RecordReader + ?Sized,
+{
+    let mut result = Comparison::default();
+    let mut left_records: HashMap<u64, TxRecord> = HashMap::new();
+    while let Some(record) = left.read_record()? {
+        match left_records.entry(record.tx_id) {
+            Entry::Occupied(_) => result.left_duplicates.push(record.tx_id),
+            Entry::Vacant(entry) => {
+                entry.insert(record);
+            }
+        }
+    }
+
+    let mut right_ids = HashSet::new();
+    while let Some(record) = right.read_record()? {
+        if !right_ids.insert(record.tx_id) {
+            result.right_duplicates.push(record.tx_id);
+            continue;
+        }
+        let Some(expected) = left_records.remove(&record.tx_id) else {
+            result.extra.push(record.tx_id);
+            continue;
+        };
+
+        let mismatches = field_values(&expected)
+            .into_iter()
+            .zip(field_values(&record))
+            .filter(|((field, l), (_, r))| l != r && !ignored.contains(field))
+            .map(|((field, left), (_, right))| FieldMismatch {
+                tx_id: record.tx_id,
+                field,
+                left,
+                right,
+            });
+        result.mismatches.extend(mismatches);
+    }
+
+    result.missing = left_records.into_keys().collect();
+    result.missing.sort_unstable();
+    result.extra.sort_unstable();
+    for duplicates in [&mut result.left_duplicates, &mut result.right_duplicates] {
+        duplicates.sort_unstable();
+        duplicates.dedup();
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::Format;
+
+    const EXAMPLE_CSV: &[u8] = include_bytes!("../../data/records_example.csv");
+    const EXAMPLE_TEXT: &[u8] = include_bytes!("../../data/records_example.txt");
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../data/records_example.bin");
+
+    #[test]
+    fn test_compare_equal_files() {
+        let result = compare(
+            &mut Format::Csv.reader(EXAMPLE_CSV),
+            &mut Format::Text.reader(EXAMPLE_TEXT),
+            &[],
+        )
+        .unwrap();
+        assert!(result.is_equal());
+    }
+
+    #[test]
+    fn test_compare_bin_example_descriptions() {
+        let result = compare(
+            &mut Format::Csv.reader(EXAMPLE_CSV),
+            &mut Format::Bin.reader(EXAMPLE_BIN),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(result.mismatches.len(), 1000);
+        assert_eq!(
+            result.mismatches[0],
+            FieldMismatch {
+                tx_id: 1000000000000000,
+                field: "DESCRIPTION",
+                left: "Record number 1".into(),
+                right: "\"Record number 1\"".into(),
+            }
+        );
+
+        let result = compare(
+            &mut Format::Csv.reader(EXAMPLE_CSV),
+            &mut Format::Bin.reader(EXAMPLE_BIN),
+            &["DESCRIPTION"],
+        )
+        .unwrap();
+        assert!(result.is_equal());
+    }
+
+    #[test]
+    fn test_compare_missing_extra_mismatch() {
+        let mut left = Format::Csv.reader(EXAMPLE_CSV).read_all().unwrap();
+        let mut right = left.clone();
+        left.truncate(3);
+        right.truncate(4);
+        right.remove(0);
+        right[0].amount = 1;
+
+        let result = compare(&mut left.into_iter(), &mut right.into_iter(), &[]).unwrap();
+        assert_eq!(result.missing, vec![1000000000000000]);
+        assert_eq!(result.extra, vec![1000000000000003]);
+        assert_eq!(result.mismatches.len(), 1);
+        assert_eq!(result.mismatches[0].field, "AMOUNT");
+        assert_eq!(result.mismatches[0].right, "1");
+    }
+
+    #[test]
+    fn test_compare_duplicates() {
+        let records = Format::Csv.reader(EXAMPLE_CSV).read_all().unwrap();
+        let mut left = records[..3].to_vec();
+        let mut changed = records[1].clone();
+        changed.amount = 1;
+        left.push(changed);
+        let mut right = records[..3].to_vec();
+        right.push(records[2].clone());
+        right.push(records[2].clone());
+
+        let result = compare(&mut left.into_iter(), &mut right.into_iter(), &[]).unwrap();
+        assert!(!result.is_equal());
+        assert_eq!(result.left_duplicates, vec![records[1].tx_id]);
+        assert_eq!(result.right_duplicates, vec![records[2].tx_id]);
+        // Сравнивается первая запись с повторяющимся TX_ID
+        assert!(result.mismatches.is_empty());
+        assert!(result.missing.is_empty() && result.extra.is_empty());
+    }
+}
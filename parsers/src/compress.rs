@@ -0,0 +1,290 @@
+//! Прозрачное сжатие файлов YPBank: gzip и zstd
+//!
+//! При чтении сжатие определяется по первым байтам, при записи - по расширению пути.
+//! Данные распаковываются и сжимаются потоком, файл целиком в память не загружается.
+
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Write},
+    path::Path,
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+
+/// Сжатие файла
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Сжатие по расширению: `.gz` или `.zst`
+    pub fn from_path(path: impl AsRef<Path>) -> Self {
+        match path.as_ref().extension().and_then(|e| e.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Сжатие по первым байтам потока
+    pub fn detect(start: &[u8]) -> Self {
+        if start.starts_with(&GZIP_MAGIC) {
+            Compression::Gzip
+        } else if start.starts_with(&ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+impl Display for Compression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Распаковывающий читатель. Сжатие определяется по первым байтам, не потребляя их,
+/// несжатый поток возвращается как есть
+pub fn decompress<'a, R: BufRead + 'a>(mut inner: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    Ok(match Compression::detect(inner.fill_buf()?) {
+        Compression::None => Box::new(inner),
+        Compression::Gzip => Box::new(BufReader::new(MultiGzDecoder::new(inner))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::with_buffer(inner)?)),
+    })
+}
+
+/// Открывает файл на чтение с распаковкой
+pub fn open(path: impl AsRef<Path>) -> io::Result<Box<dyn BufRead>> {
+    decompress(BufReader::new(File::open(path)?))
+}
+
+/// Создаёт файл на запись, сжатие выбирается по расширению
+pub fn create(path: impl AsRef<Path>) -> io::Result<CompressWriter<BufWriter<File>>> {
+    let compression = Compression::from_path(&path);
+    Ok(CompressWriter::new(
+        BufWriter::new(File::create(path)?),
+        compression,
+    ))
+}
+
+enum Stream<W: Write> {
+    /// Между сжатыми кадрами: следующая запись начнёт новый
+    Idle(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+/// Сжимающий писатель
+///
+/// `flush` только сбрасывает сжатые данные, кадр (член gzip или кадр zstd) завершают
+/// [CompressWriter::finish] и [CompressWriter::into_inner]. Запись после `finish` начинает
+/// новый кадр, распаковка читает такую склейку как один поток.
+/// Незавершённый кадр дописывается и при удалении писателя, но ошибки тогда теряются.
+pub struct CompressWriter<W: Write> {
+    compression: Compression,
+    /// `None` - писатель сломан ошибкой сжатия
+    stream: Option<Stream<W>>,
+}
+
+impl<W: Write> CompressWriter<W> {
+    pub fn new(inner: W, compression: Compression) -> Self {
+        Self {
+            compression,
+            stream: Some(Stream::Idle(inner)),
+        }
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Завершает текущий кадр и сбрасывает внутренний писатель
+    pub fn finish(&mut self) -> io::Result<()> {
+        let mut inner = match self.stream.take() {
+            Some(Stream::Idle(inner)) => inner,
+            Some(Stream::Gzip(encoder)) => encoder.finish()?,
+            Some(Stream::Zstd(encoder)) => encoder.finish()?,
+            None => return Err(broken()),
+        };
+        let result = inner.flush();
+        self.stream = Some(Stream::Idle(inner));
+        result
+    }
+
+    /// Завершает кадр и возвращает внутренний писатель
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.finish()?;
+        match self.stream.take() {
+            Some(Stream::Idle(inner)) => Ok(inner),
+            _ => Err(broken()),
+        }
+    }
+
+    fn stream(&mut self) -> io::Result<&mut Stream<W>> {
+        if let Some(Stream::Idle(_)) = self.stream
+            && self.compression != Compression::None
+        {
+            let Some(Stream::Idle(inner)) = self.stream.take() else {
+                unreachable!()
+            };
+            self.stream = Some(match self.compression {
+                Compression::Gzip => {
+                    Stream::Gzip(GzEncoder::new(inner, flate2::Compression::default()))
+                }
+                _ => Stream::Zstd(zstd::Encoder::new(inner, 0)?),
+            });
+        }
+        self.stream.as_mut().ok_or_else(broken)
+    }
+}
+
+fn broken() -> io::Error {
+    io::Error::other("писатель сломан предыдущей ошибкой сжатия")
+}
+
+impl<W: Write> Write for CompressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.stream()? {
+            Stream::Idle(inner) => inner.write(buf),
+            Stream::Gzip(encoder) => encoder.write(buf),
+            Stream::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.stream.as_mut().ok_or_else(broken)? {
+            Stream::Idle(inner) => inner.flush(),
+            Stream::Gzip(encoder) => encoder.flush(),
+            Stream::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+impl<W: Write> Drop for CompressWriter<W> {
+    fn drop(&mut self) {
+        if !matches!(self.stream, Some(Stream::Idle(_)) | None) {
+            let _ = self.finish();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, RecordReader, RecordWriter};
+    use std::io::Read;
+
+    const EXAMPLE_CSV: &[u8] = include_bytes!("../../data/records_example.csv");
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../data/records_example.bin");
+
+    fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+        let mut writer = CompressWriter::new(Vec::new(), compression);
+        writer.write_all(data).unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    fn read_all(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        decompress(data).unwrap().read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_compression_detect() {
+        assert_eq!(Compression::from_path("a.bin.gz"), Compression::Gzip);
+        assert_eq!(Compression::from_path("a.csv.zst"), Compression::Zstd);
+        assert_eq!(Compression::from_path("a.csv"), Compression::None);
+
+        for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+            let data = compress(EXAMPLE_CSV, compression);
+            assert_eq!(Compression::detect(&data), compression);
+            assert_eq!(read_all(&data), EXAMPLE_CSV);
+        }
+        assert!(compress(EXAMPLE_CSV, Compression::Zstd).len() < EXAMPLE_CSV.len() / 2);
+    }
+
+    #[test]
+    fn test_compressed_records_round_trip() {
+        let records = Format::Bin.reader(EXAMPLE_BIN).read_all().unwrap();
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let mut out = CompressWriter::new(Vec::new(), compression);
+            let mut writer = Format::Text.writer(&mut out);
+            writer.write_all(&records[..500]).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+            // Запись после finish начинает новый кадр
+            out.finish().unwrap();
+            let mut writer = Format::Text.writer(&mut out);
+            writer.write_all(&records[500..]).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+            let data = out.into_inner().unwrap();
+
+            let decoded = Format::Text
+                .reader(decompress(data.as_slice()).unwrap())
+                .read_all()
+                .unwrap();
+            assert_eq!(decoded, records, "{}", compression);
+        }
+    }
+
+    #[test]
+    fn test_compress_flush_keeps_frame() {
+        for compression in [Compression::Gzip, Compression::Zstd] {
+            let mut writer = CompressWriter::new(Vec::new(), compression);
+            writer.write_all(b"YP").unwrap();
+            writer.flush().unwrap();
+            assert!(!matches!(writer.stream, Some(Stream::Idle(_))));
+            writer.write_all(b"Bank").unwrap();
+            writer.flush().unwrap();
+            let data = writer.into_inner().unwrap();
+
+            assert_eq!(read_all(&data), b"YPBank");
+        }
+
+        // Один член gzip: его читает и распаковщик без склейки
+        let mut writer = CompressWriter::new(Vec::new(), Compression::Gzip);
+        writer.write_all(b"YP").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"Bank").unwrap();
+        let data = writer.into_inner().unwrap();
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(data.as_slice())
+            .read_to_end(&mut out)
+            .unwrap();
+        assert_eq!(out, b"YPBank");
+    }
+
+    #[test]
+    fn test_compress_finishes_on_drop() {
+        let mut data = Vec::new();
+        {
+            let mut writer = CompressWriter::new(&mut data, Compression::Gzip);
+            writer.write_all(b"YPBank").unwrap();
+        }
+        assert_eq!(read_all(&data), b"YPBank");
+
+        let truncated = compress(EXAMPLE_CSV, Compression::Gzip);
+        let mut out = Vec::new();
+        let result = decompress(&truncated[..truncated.len() - 8])
+            .unwrap()
+            .read_to_end(&mut out);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,391 @@
+use std::{fmt::Display, io, ops::Range, str::FromStr};
+
+/// Позиция ошибки во входе
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Position {
+    /// Строка и столбец (с 1) в текстовых форматах
+    Line { line: usize, column: usize },
+
+    /// Смещение в байтах от начала потока в бинарном формате
+    Offset(u64),
+}
+
+/// Фрагмент входа, в котором найдена ошибка
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    /// Строка текста или байты записи в hex
+    pub text: String,
+    /// Начало выделения внутри `text`, в символах
+    pub start: usize,
+    /// Длина выделения в символах, `0` - без выделения
+    pub len: usize,
+}
+
+impl Snippet {
+    pub fn new(text: impl Into<String>, start: usize, len: usize) -> Self {
+        Self {
+            text: text.into(),
+            start,
+            len,
+        }
+    }
+
+    /// Фрагмент строки, `start` и `len` - в байтах строки
+    pub fn line(text: &str, start: usize, len: usize) -> Self {
+        let start_chars = text[..start].chars().count();
+        let len_chars = text[start..start + len].chars().count();
+        Self::new(text, start_chars, len_chars)
+    }
+
+    /// Байты в hex, `start` и `len` - в байтах
+    pub fn hex(bytes: &[u8], start: usize, len: usize) -> Self {
+        let text = bytes
+            .iter()
+            .map(|b| format!("{:02X}", b))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let len = if len == 0 { 0 } else { len * 3 - 1 };
+        Self::new(text, start * 3, len)
+    }
+}
+
+/// Строка текстового входа, относительно которой строятся позиции ошибок
+pub struct Line<'a> {
+    pub number: usize,
+    pub text: &'a str,
+}
+
+impl<'a> Line<'a> {
+    pub fn new(number: usize, text: &'a str) -> Self {
+        Self { number, text }
+    }
+
+    /// Строка из байт, прочитанных до `\n` включительно, без перевода строки.
+    /// Неверный UTF-8 - ошибка со столбцом первого неверного байта, а не ошибка ввода-вывода:
+    /// строка уже прочитана, и нестрогий режим её пропускает
+    pub fn decode(number: usize, bytes: &'a [u8]) -> Result<Self, ParseError> {
+        Self::decode_with(number, bytes, |_, _| None)
+    }
+
+    /// Как [Line::decode], но ошибка относится к полю: `field` получает строку,
+    /// где неверные байты заменены на `U+FFFD`, и смещение первого из них в байтах,
+    /// и возвращает имя поля и байты его значения
+    pub fn decode_with(
+        number: usize,
+        bytes: &'a [u8],
+        field: impl FnOnce(&str, usize) -> Option<(&'static str, Range<usize>)>,
+    ) -> Result<Self, ParseError> {
+        let end = bytes
+            .iter()
+            .rposition(|b| !matches!(b, b'\n' | b'\r'))
+            .map_or(0, |last| last + 1);
+        let bytes = &bytes[..end];
+        let err = match std::str::from_utf8(bytes) {
+            Ok(text) => return Ok(Self::new(number, text)),
+            Err(err) => err,
+        };
+
+        let text = String::from_utf8_lossy(bytes);
+        let at = err.valid_up_to();
+        let column = text[..at].chars().count() + 1;
+        let snippet = Snippet::line(&text, at, char::REPLACEMENT_CHARACTER.len_utf8());
+        let error = match field(&text, at) {
+            Some((name, value)) => {
+                ParseError::new(ParseErrorKind::InvalidValue(text[value].to_string()))
+                    .with_field(name)
+            }
+            None => ParseError::new(ParseErrorKind::InvalidLine),
+        };
+        Err(error.at_line(number, column).with_snippet(snippet))
+    }
+
+    /// Ошибка о фрагменте `part` - подстроке `text`
+    pub fn error(&self, kind: ParseErrorKind, part: &str) -> ParseError {
+        let start = part.as_ptr() as usize - self.text.as_ptr() as usize;
+        let column = self.text[..start].chars().count() + 1;
+        ParseError::new(kind)
+            .at_line(self.number, column)
+            .with_snippet(Snippet::line(self.text, start, part.len()))
+    }
+
+    /// Разбирает значение поля `part`
+    pub fn parse<T: FromStr>(&self, field: &'static str, part: &str) -> Result<T, ParseError> {
+        part.parse::<T>().map_err(|_| {
+            self.error(ParseErrorKind::InvalidValue(part.to_string()), part)
+                .with_field(field)
+        })
+    }
+}
+
+/// Вид ошибки парсинга
+#[derive(Debug)]
+pub enum ParseErrorKind {
+    /// Ошибка ввода-вывода
+    Io(io::Error),
+
+    /// Заголовок не совпадает со спецификацией
+    InvalidHeader,
+
+    /// Строка не разбирается на поля
+    InvalidLine,
+
+    /// Неверное значение поля
+    InvalidValue(String),
+
+    /// Неизвестное поле
+    UnknownField(String),
+
+    /// Поле встречается в записи повторно
+    DuplicateField { first_line: usize },
+
+    /// В записи нет обязательного поля
+    MissingField,
+
+    /// Запись бинарного файла не начинается с MAGIC
+    InvalidMagic,
+
+    /// Недопустимый RECORD_SIZE
+    InvalidRecordSize(u32),
+
+    /// Файл оборвался посреди записи
+    UnexpectedEof,
+
+    /// CRC32 записи не совпадает с содержимым
+    ChecksumMismatch { expected: u32, actual: u32 },
+}
+
+/// Ошибка парсинга с позицией, полем и фрагментом входа
+#[derive(Debug)]
+pub struct ParseError {
+    pub kind: ParseErrorKind,
+    pub position: Option<Position>,
+    pub field: Option<&'static str>,
+    pub snippet: Option<Snippet>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind) -> Self {
+        Self {
+            kind,
+            position: None,
+            field: None,
+            snippet: None,
+        }
+    }
+
+    /// Ошибка ввода-вывода: после неё чтение продолжить нельзя
+    pub fn is_io(&self) -> bool {
+        matches!(self.kind, ParseErrorKind::Io(_))
+    }
+
+    pub fn at(mut self, position: Position) -> Self {
+        self.position = Some(position);
+        self
+    }
+
+    pub fn at_line(self, line: usize, column: usize) -> Self {
+        self.at(Position::Line { line, column })
+    }
+
+    pub fn at_offset(self, offset: u64) -> Self {
+        self.at(Position::Offset(offset))
+    }
+
+    pub fn with_field(mut self, field: &'static str) -> Self {
+        self.field = Some(field);
+        self
+    }
+
+    pub fn with_snippet(mut self, snippet: Snippet) -> Self {
+        self.snippet = Some(snippet);
+        self
+    }
+
+    /// Номер строки для текстовых форматов
+    pub fn line(&self) -> Option<usize> {
+        match self.position {
+            Some(Position::Line { line, .. }) => Some(line),
+            _ => None,
+        }
+    }
+
+    /// Смещение для бинарного формата
+    pub fn offset(&self) -> Option<u64> {
+        match self.position {
+            Some(Position::Offset(offset)) => Some(offset),
+            _ => None,
+        }
+    }
+
+    /// Текст ошибки без позиции
+    pub fn message(&self) -> String {
+        let field = self.field.unwrap_or("?");
+        match &self.kind {
+            ParseErrorKind::Io(err) => format!("ошибка ввода-вывода: {}", err),
+            ParseErrorKind::InvalidHeader => "неверный заголовок".to_string(),
+            ParseErrorKind::InvalidLine => "строка не разбирается на поля".to_string(),
+            ParseErrorKind::InvalidValue(value) => {
+                format!("неверное значение поля {}: {:?}", field, value)
+            }
+            ParseErrorKind::UnknownField(name) => format!("неизвестное поле {}", name),
+            ParseErrorKind::DuplicateField { first_line } => {
+                format!("поле {} уже было на строке {}", field, first_line)
+            }
+            ParseErrorKind::MissingField => format!("нет поля {}", field),
+            ParseErrorKind::InvalidMagic => "нет MAGIC 'YPBN' в начале записи".to_string(),
+            ParseErrorKind::InvalidRecordSize(size) => {
+                format!("недопустимый размер записи {}", size)
+            }
+            ParseErrorKind::UnexpectedEof => "неожиданный конец файла".to_string(),
+            ParseErrorKind::ChecksumMismatch { expected, actual } => format!(
+                "контрольная сумма записи не совпадает: в файле {:08X}, по данным {:08X}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+/// Выводит ошибку в виде диагностики компилятора:
+///
+/// ```text
+/// ошибка: неверное значение поля AMOUNT: "abc"
+///  --> строка 2, столбец 15
+///   |
+/// 2 | 1,DEPOSIT,0,1,abc,0,SUCCESS,""
+///   |               ^^^
+/// ```
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "ошибка: {}", self.message())?;
+
+        let label = match self.position {
+            Some(Position::Line { line, column }) => {
+                write!(f, "\n --> строка {}, столбец {}", line, column)?;
+                line.to_string()
+            }
+            Some(Position::Offset(offset)) => {
+                write!(f, "\n --> смещение {} (0x{:X})", offset, offset)?;
+                String::new()
+            }
+            None => String::new(),
+        };
+
+        if let Some(snippet) = &self.snippet {
+            let pad = " ".repeat(label.chars().count());
+            write!(f, "\n{} |\n{} | {}", pad, label, snippet.text)?;
+            if snippet.len > 0 {
+                write!(
+                    f,
+                    "\n{} | {}{}",
+                    pad,
+                    " ".repeat(snippet.start),
+                    "^".repeat(snippet.len)
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Режим разбора
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParseMode {
+    /// Разбор останавливается на первой ошибке
+    #[default]
+    Strict,
+
+    /// Неверные записи пропускаются, ошибки собираются в отчёт.
+    /// Ошибки ввода-вывода прерывают разбор и в этом режиме
+    Lenient,
+}
+
+/// Результат разбора: корректные данные и ошибки пропущенных записей
+#[derive(Debug)]
+pub struct ParseReport<T> {
+    pub data: T,
+    pub errors: Vec<ParseError>,
+}
+
+impl<T> ParseReport<T> {
+    /// Ошибок не было
+    pub fn is_clean(&self) -> bool {
+        self.errors.is_empty()
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(err: io::Error) -> Self {
+        ParseError::new(ParseErrorKind::Io(err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_line_diagnostic() {
+        let text = "1,DEPOSIT,0,1,abc,0,SUCCESS,\"\"";
+        let err = ParseError::new(ParseErrorKind::InvalidValue("abc".into()))
+            .at_line(2, 15)
+            .with_field("AMOUNT")
+            .with_snippet(Snippet::line(text, 14, 3));
+
+        assert_eq!(
+            err.to_string(),
+            "ошибка: неверное значение поля AMOUNT: \"abc\"
+ --> строка 2, столбец 15
+  |
+2 | 1,DEPOSIT,0,1,abc,0,SUCCESS,\"\"
+  |               ^^^"
+        );
+    }
+
+    #[test]
+    fn test_render_invalid_utf8() {
+        let err = Line::decode(3, b"1,DEPOSIT,0,1,10,0,SUCCESS,\"\xD0\xB0\xFF\"\r\n")
+            .err()
+            .unwrap();
+        assert_eq!(
+            err.to_string(),
+            "ошибка: строка не разбирается на поля
+ --> строка 3, столбец 30
+  |
+3 | 1,DEPOSIT,0,1,10,0,SUCCESS,\"а\u{FFFD}\"
+  |                              ^"
+        );
+
+        let err = Line::decode_with(1, b"AMOUNT: 1\xFF0", |text, _| {
+            Some(("AMOUNT", 8..text.len()))
+        })
+        .err()
+        .unwrap();
+        assert_eq!(err.field, Some("AMOUNT"));
+        assert_eq!(
+            err.to_string(),
+            "ошибка: неверное значение поля AMOUNT: \"1\u{FFFD}0\"
+ --> строка 1, столбец 10
+  |
+1 | AMOUNT: 1\u{FFFD}0
+  |          ^"
+        );
+    }
+
+    #[test]
+    fn test_render_offset_diagnostic() {
+        let err = ParseError::new(ParseErrorKind::InvalidMagic)
+            .at_offset(72)
+            .with_snippet(Snippet::hex(b"XPBN\x00\x00\x00\x3F", 0, 4));
+
+        assert_eq!(
+            err.to_string(),
+            "ошибка: нет MAGIC 'YPBN' в начале записи
+ --> смещение 72 (0x48)
+ |
+ | 58 50 42 4E 00 00 00 3F
+ | ^^^^^^^^^^^"
+        );
+    }
+}
@@ -0,0 +1,775 @@
+use super::{RecordReader, RecordWriter};
+use crate::{
+    errors::{ParseError, ParseErrorKind, ParseMode, Snippet},
+    record::{TxRecord, TxRecordRef, TxStatus, TxType},
+};
+use std::{
+    fmt::Display,
+    io::{ErrorKind, Read, Write},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Начало каждой записи - `'YPBN'`
+pub const MAGIC: [u8; 4] = *b"YPBN";
+
+/// Начало файла версии 2 - `'YPBF'`
+pub const FILE_MAGIC: [u8; 4] = *b"YPBF";
+
+/// Размер заголовка файла версии 2: FILE_MAGIC + VERSION + CREATED_AT
+pub const FILE_HEADER_SIZE: usize = 16;
+
+/// Размер CRC32 в конце записи версии 2
+pub const CRC_SIZE: usize = 4;
+
+/// Размер заголовка записи: MAGIC + RECORD_SIZE
+pub const HEADER_SIZE: usize = 8;
+
+/// Размер тела записи без описания
+pub const BODY_FIXED_SIZE: usize = 46;
+
+/// Ограничение на RECORD_SIZE, чтобы испорченный заголовок не заставил читать гигабайты
+pub const MAX_RECORD_SIZE: u32 = BODY_FIXED_SIZE as u32 + (1 << 20);
+
+/// Сколько байт читается из потока за раз
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Сколько байт вокруг ошибки показывать в диагностике
+const SNIPPET_CONTEXT: usize = 4;
+
+/// Версия формата YPBankBin
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BinVersion {
+    /// Поток записей без заголовка файла
+    #[default]
+    V1,
+
+    /// Заголовок файла с версией и временем создания, CRC32 в конце каждой записи
+    V2,
+}
+
+impl BinVersion {
+    pub fn number(self) -> u32 {
+        match self {
+            BinVersion::V1 => 1,
+            BinVersion::V2 => 2,
+        }
+    }
+
+    /// Сколько байт идёт после тела записи
+    pub(crate) fn trailer_size(self) -> usize {
+        match self {
+            BinVersion::V1 => 0,
+            BinVersion::V2 => CRC_SIZE,
+        }
+    }
+}
+
+impl Display for BinVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.number())
+    }
+}
+
+impl FromStr for BinVersion {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "1" | "v1" => Ok(BinVersion::V1),
+            "2" | "v2" => Ok(BinVersion::V2),
+            _ => Err(format!("неизвестная версия YPBankBin: {}", value)),
+        }
+    }
+}
+
+/// Смещения полей внутри тела записи
+const TX_TYPE_OFFSET: usize = 8;
+const STATUS_OFFSET: usize = 41;
+const DESC_LEN_OFFSET: usize = 42;
+
+impl TxType {
+    /// Код типа в бинарном формате
+    pub fn to_byte(self) -> u8 {
+        match self {
+            TxType::Deposit => 0,
+            TxType::Transfer => 1,
+            TxType::Withdrawal => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(TxType::Deposit),
+            1 => Some(TxType::Transfer),
+            2 => Some(TxType::Withdrawal),
+            _ => None,
+        }
+    }
+}
+
+impl TxStatus {
+    /// Код статуса в бинарном формате
+    pub fn to_byte(self) -> u8 {
+        match self {
+            TxStatus::Success => 0,
+            TxStatus::Failure => 1,
+            TxStatus::Pending => 2,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> Option<Self> {
+        match byte {
+            0 => Some(TxStatus::Success),
+            1 => Some(TxStatus::Failure),
+            2 => Some(TxStatus::Pending),
+            _ => None,
+        }
+    }
+}
+
+/// Запись, пропущенная в нестрогом режиме
+#[derive(Debug)]
+pub struct SkippedRecord {
+    /// Смещение испорченной записи от начала потока
+    pub offset: u64,
+    pub error: ParseError,
+}
+
+/// Чтение записей формата YPBankBin
+///
+/// В нестрогом режиме (ресинхронизация) испорченная запись пропускается,
+/// а чтение продолжается со следующего MAGIC.
+pub struct BinReader<R: Read> {
+    inner: R,
+    /// Буфер чтения: байты `start..end` прочитаны, но ещё не разобраны
+    buf: Vec<u8>,
+    start: usize,
+    end: usize,
+    /// Смещение `buf[start]` от начала потока
+    offset: u64,
+    eof: bool,
+    mode: ParseMode,
+    skipped: Vec<SkippedRecord>,
+    /// Заголовок файла уже прочитан
+    started: bool,
+    version: BinVersion,
+    created_at: Option<u64>,
+}
+
+impl<R: Read> BinReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_mode(inner, ParseMode::Strict)
+    }
+
+    pub fn with_mode(inner: R, mode: ParseMode) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            start: 0,
+            end: 0,
+            offset: 0,
+            eof: false,
+            mode,
+            skipped: Vec::new(),
+            started: false,
+            version: BinVersion::V1,
+            created_at: None,
+        }
+    }
+
+    /// Читатель, пропускающий испорченные записи
+    pub fn with_resync(inner: R) -> Self {
+        Self::with_mode(inner, ParseMode::Lenient)
+    }
+
+    /// Пропущенные в нестрогом режиме записи
+    pub fn skipped(&self) -> &[SkippedRecord] {
+        &self.skipped
+    }
+
+    /// Версия файла, известна после первого чтения
+    pub fn version(&self) -> BinVersion {
+        self.version
+    }
+
+    /// Время создания файла версии 2 в миллисекундах, известно после первого чтения
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Читает заголовок файла версии 2, если он есть
+    fn read_file_header(&mut self) -> Result<(), ParseError> {
+        self.started = true;
+        if !self.fill(FILE_MAGIC.len())? || !self.pending().starts_with(&FILE_MAGIC) {
+            return Ok(());
+        }
+        if !self.fill(FILE_HEADER_SIZE)? {
+            let pending = self.pending();
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                self.offset,
+                pending,
+                0,
+                0,
+            ));
+        }
+        self.created_at = Some(check_file_header(&self.pending()[..FILE_HEADER_SIZE])?);
+        self.version = BinVersion::V2;
+        self.consume(FILE_HEADER_SIZE);
+        Ok(())
+    }
+
+    fn try_read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        if !self.fill(HEADER_SIZE)? {
+            if self.pending().is_empty() {
+                return Ok(None);
+            }
+            let pending = self.pending();
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                self.offset,
+                pending,
+                0,
+                0,
+            ));
+        }
+        let trailer = self.version.trailer_size();
+        let len = check_header(self.offset, &self.pending()[..HEADER_SIZE])? + trailer;
+        if !self.fill(len)? {
+            let pending = self.pending();
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                self.offset,
+                &pending[..HEADER_SIZE],
+                0,
+                0,
+            ));
+        }
+        if self.version == BinVersion::V2 {
+            check_crc(self.offset, &self.pending()[..len])?;
+        }
+        let record = decode_body(
+            self.offset + HEADER_SIZE as u64,
+            &self.pending()[HEADER_SIZE..len - trailer],
+        )?
+        .into();
+
+        self.consume(len);
+        Ok(Some(record))
+    }
+
+    /// Ещё не разобранные байты
+    fn pending(&self) -> &[u8] {
+        &self.buf[self.start..self.end]
+    }
+
+    /// Дочитывает поток, пока в буфере не будет `len` байт. `false` - поток кончился раньше
+    fn fill(&mut self, len: usize) -> Result<bool, ParseError> {
+        if self.end - self.start >= len {
+            return Ok(true);
+        }
+        // Сдвигаем остаток в начало, чтобы буфер не рос вместе с файлом
+        self.buf.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        if self.buf.len() < len.max(CHUNK_SIZE) {
+            self.buf.resize(len.max(CHUNK_SIZE), 0);
+        }
+
+        while self.end < len && !self.eof {
+            match self.inner.read(&mut self.buf[self.end..]) {
+                Ok(0) => self.eof = true,
+                Ok(read) => self.end += read,
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(self.end >= len)
+    }
+
+    /// Пропускает байты до следующего MAGIC после текущей позиции
+    fn seek_magic(&mut self) -> Result<(), ParseError> {
+        let mut from = 1;
+        loop {
+            let pending = self.pending();
+            if let Some(pos) = pending[from.min(pending.len())..]
+                .windows(MAGIC.len())
+                .position(|w| w == MAGIC)
+            {
+                self.consume(from + pos);
+                return Ok(());
+            }
+            // Хвост буфера может оказаться началом MAGIC
+            let keep = (MAGIC.len() - 1).min(pending.len());
+            self.consume(pending.len() - keep);
+            if self.eof {
+                self.consume(self.pending().len());
+                return Ok(());
+            }
+            let len = self.pending().len() + 1;
+            self.fill(len)?;
+            from = 0;
+        }
+    }
+
+    fn consume(&mut self, len: usize) {
+        self.start += len;
+        self.offset += len as u64;
+    }
+}
+
+impl<R: Read> RecordReader for BinReader<R> {
+    /// Ошибка в заголовке файла возвращается и в нестрогом режиме
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        if !self.started {
+            self.read_file_header()?;
+        }
+        loop {
+            match self.try_read_record() {
+                Err(err) if err.is_io() => return Err(err),
+                Err(error) if self.mode == ParseMode::Lenient => {
+                    self.skipped.push(SkippedRecord {
+                        offset: self.offset,
+                        error,
+                    });
+                    self.seek_magic()?;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Ошибки пропущенных записей, [BinReader::skipped] после этого пуст
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        self.skipped
+            .drain(..)
+            .map(|skipped| skipped.error)
+            .collect()
+    }
+}
+
+/// Ошибка о байтах `bytes[start..start + len]`, `base` - смещение `bytes` от начала потока
+pub(crate) fn bytes_error(
+    kind: ParseErrorKind,
+    base: u64,
+    bytes: &[u8],
+    start: usize,
+    len: usize,
+) -> ParseError {
+    let from = start.saturating_sub(SNIPPET_CONTEXT);
+    let to = (start + len + SNIPPET_CONTEXT).min(bytes.len());
+    ParseError::new(kind)
+        .at_offset(base + start as u64)
+        .with_snippet(Snippet::hex(&bytes[from..to], start - from, len))
+}
+
+/// Проверяет заголовок записи, возвращает длину записи вместе с заголовком.
+/// `offset` - смещение заголовка от начала потока
+pub(crate) fn check_header(offset: u64, header: &[u8]) -> Result<usize, ParseError> {
+    if header[..4] != MAGIC {
+        return Err(bytes_error(
+            ParseErrorKind::InvalidMagic,
+            offset,
+            header,
+            0,
+            4,
+        ));
+    }
+
+    let size = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    if !(BODY_FIXED_SIZE as u32..=MAX_RECORD_SIZE).contains(&size) {
+        return Err(bytes_error(
+            ParseErrorKind::InvalidRecordSize(size),
+            offset,
+            header,
+            4,
+            4,
+        )
+        .with_field("RECORD_SIZE"));
+    }
+    Ok(HEADER_SIZE + size as usize)
+}
+
+/// Проверяет заголовок файла версии 2, начинающийся с FILE_MAGIC, возвращает CREATED_AT
+pub(crate) fn check_file_header(header: &[u8]) -> Result<u64, ParseError> {
+    let version = u32::from_be_bytes(header[4..8].try_into().unwrap());
+    if version != BinVersion::V2.number() {
+        return Err(bytes_error(
+            ParseErrorKind::InvalidValue(version.to_string()),
+            0,
+            header,
+            4,
+            4,
+        )
+        .with_field("VERSION"));
+    }
+    Ok(u64::from_be_bytes(header[8..16].try_into().unwrap()))
+}
+
+/// Сверяет CRC32 записи версии 2. `record` - запись с заголовком и CRC32,
+/// `offset` - её смещение от начала потока
+pub(crate) fn check_crc(offset: u64, record: &[u8]) -> Result<(), ParseError> {
+    let (data, crc) = record.split_at(record.len() - CRC_SIZE);
+    let expected = u32::from_be_bytes(crc.try_into().unwrap());
+    let actual = crc32fast::hash(data);
+    if expected != actual {
+        return Err(bytes_error(
+            ParseErrorKind::ChecksumMismatch { expected, actual },
+            offset,
+            record,
+            data.len(),
+            CRC_SIZE,
+        )
+        .with_field("CRC32"));
+    }
+    Ok(())
+}
+
+/// Разбирает тело записи без копирования описания. `offset` - смещение тела от начала потока
+pub(crate) fn decode_body(offset: u64, body: &[u8]) -> Result<TxRecordRef<'_>, ParseError> {
+    let u64_at = |pos: usize| u64::from_be_bytes(body[pos..pos + 8].try_into().unwrap());
+    let invalid = |field, value: String, pos: usize, len: usize| {
+        bytes_error(ParseErrorKind::InvalidValue(value), offset, body, pos, len).with_field(field)
+    };
+
+    let tx_type = TxType::from_byte(body[TX_TYPE_OFFSET]).ok_or_else(|| {
+        invalid(
+            "TX_TYPE",
+            body[TX_TYPE_OFFSET].to_string(),
+            TX_TYPE_OFFSET,
+            1,
+        )
+    })?;
+    let status = TxStatus::from_byte(body[STATUS_OFFSET])
+        .ok_or_else(|| invalid("STATUS", body[STATUS_OFFSET].to_string(), STATUS_OFFSET, 1))?;
+
+    let desc_len =
+        u32::from_be_bytes(body[DESC_LEN_OFFSET..BODY_FIXED_SIZE].try_into().unwrap()) as usize;
+    if BODY_FIXED_SIZE + desc_len != body.len() {
+        return Err(invalid(
+            "DESC_LEN",
+            desc_len.to_string(),
+            DESC_LEN_OFFSET,
+            4,
+        ));
+    }
+    let description = std::str::from_utf8(&body[BODY_FIXED_SIZE..]).map_err(|err| {
+        invalid(
+            "DESCRIPTION",
+            err.to_string(),
+            BODY_FIXED_SIZE + err.valid_up_to(),
+            err.error_len().unwrap_or(1),
+        )
+    })?;
+
+    Ok(TxRecordRef {
+        tx_id: u64_at(0),
+        tx_type,
+        from_user_id: u64_at(9),
+        to_user_id: u64_at(17),
+        amount: u64_at(25) as i64,
+        timestamp: u64_at(33),
+        status,
+        description,
+    })
+}
+
+/// Кодирует заголовок файла версии 2
+pub fn encode_file_header(created_at: u64, out: &mut Vec<u8>) {
+    out.extend_from_slice(&FILE_MAGIC);
+    out.extend_from_slice(&BinVersion::V2.number().to_be_bytes());
+    out.extend_from_slice(&created_at.to_be_bytes());
+}
+
+/// Кодирует запись версии 1 вместе с заголовком
+pub fn encode_record(record: &TxRecord, out: &mut Vec<u8>) {
+    let description = record.description.as_bytes();
+    let size = (BODY_FIXED_SIZE + description.len()) as u32;
+
+    out.extend_from_slice(&MAGIC);
+    out.extend_from_slice(&size.to_be_bytes());
+    out.extend_from_slice(&record.tx_id.to_be_bytes());
+    out.push(record.tx_type.to_byte());
+    out.extend_from_slice(&record.from_user_id.to_be_bytes());
+    out.extend_from_slice(&record.to_user_id.to_be_bytes());
+    out.extend_from_slice(&record.amount.to_be_bytes());
+    out.extend_from_slice(&record.timestamp.to_be_bytes());
+    out.push(record.status.to_byte());
+    out.extend_from_slice(&(description.len() as u32).to_be_bytes());
+    out.extend_from_slice(description);
+}
+
+/// Запись в формате YPBankBin
+///
+/// По умолчанию пишется версия 1. Заголовок файла версии 2 пишется перед первой записью.
+pub struct BinWriter<W: Write> {
+    inner: W,
+    buf: Vec<u8>,
+    /// Сколько байт уже записано
+    offset: u64,
+    version: BinVersion,
+    /// Время создания для заголовка файла, `None` - время первой записи
+    created_at: Option<u64>,
+    header_written: bool,
+}
+
+impl<W: Write> BinWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_version(inner, BinVersion::V1)
+    }
+
+    pub fn with_version(inner: W, version: BinVersion) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            offset: 0,
+            version,
+            created_at: None,
+            header_written: false,
+        }
+    }
+
+    /// CREATED_AT в заголовке версии 2, миллисекунды. Без него пишется текущее время
+    pub fn created_at(mut self, created_at: u64) -> Self {
+        self.created_at = Some(created_at);
+        self
+    }
+
+    /// Дописывание в файл, где заголовок файла уже есть: он не пишется
+    pub fn appending(mut self) -> Self {
+        self.header_written = true;
+        self
+    }
+
+    fn write_header(&mut self) -> Result<(), ParseError> {
+        if self.header_written || self.version == BinVersion::V1 {
+            return Ok(());
+        }
+        let created_at = self.created_at.unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |d| d.as_millis() as u64)
+        });
+        self.buf.clear();
+        encode_file_header(created_at, &mut self.buf);
+        self.inner.write_all(&self.buf)?;
+        self.offset += self.buf.len() as u64;
+        self.header_written = true;
+        Ok(())
+    }
+
+    /// Сбрасывает буфер и возвращает внутренний writer
+    pub fn into_inner(mut self) -> Result<W, ParseError> {
+        self.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> RecordWriter for BinWriter<W> {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        let size = BODY_FIXED_SIZE + record.description.len();
+        if size > MAX_RECORD_SIZE as usize {
+            let size = size.min(u32::MAX as usize) as u32;
+            return Err(ParseError::new(ParseErrorKind::InvalidRecordSize(size))
+                .at_offset(self.offset)
+                .with_field("DESCRIPTION"));
+        }
+        self.write_header()?;
+        self.buf.clear();
+        encode_record(record, &mut self.buf);
+        if self.version == BinVersion::V2 {
+            let crc = crc32fast::hash(&self.buf);
+            self.buf.extend_from_slice(&crc.to_be_bytes());
+        }
+        self.inner.write_all(&self.buf)?;
+        self.offset += self.buf.len() as u64;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.write_header()?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::testing::{described, encode_bin};
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    #[test]
+    fn test_bin_example_round_trip() {
+        let records = BinReader::new(EXAMPLE).read_all().unwrap();
+        assert_eq!(records.len(), 1000);
+        assert_eq!(records[0].tx_id, 1000000000000000);
+        assert_eq!(records[0].tx_type, TxType::Deposit);
+        assert_eq!(records[0].to_user_id, 9223372036854775807);
+        assert_eq!(records[0].status, TxStatus::Failure);
+
+        assert_eq!(encode_bin(&records), EXAMPLE);
+    }
+
+    #[test]
+    fn test_bin_signed_amount() {
+        let records = vec![described(1, ""), described(2, "Описание")];
+        let decoded = BinReader::new(encode_bin(&records).as_slice())
+            .read_all()
+            .unwrap();
+        assert_eq!(decoded, records);
+        assert_eq!(decoded[0].amount, -1500);
+    }
+
+    #[test]
+    fn test_bin_strict_errors() {
+        let data = encode_bin(&[described(1, "a")]);
+
+        let result = BinReader::new(&data[..data.len() - 1]).read_all();
+        let err = result.unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::UnexpectedEof);
+        assert_eq!(err.offset(), Some(0));
+
+        let mut broken = data.clone();
+        broken[0] = b'X';
+        let result = BinReader::new(broken.as_slice()).read_all();
+        let err = result.unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidMagic);
+        assert_eq!(err.offset(), Some(0));
+        assert_eq!(
+            err.snippet,
+            Some(Snippet::new("58 50 42 4E 00 00 00 2F", 0, 11))
+        );
+
+        let mut broken = data.clone();
+        broken[HEADER_SIZE + TX_TYPE_OFFSET] = 9;
+        let err = BinReader::new(broken.as_slice()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == "9");
+        assert_eq!(err.field, Some("TX_TYPE"));
+        assert_eq!(err.offset(), Some(16));
+        assert_eq!(
+            err.snippet,
+            Some(Snippet::new("00 00 00 01 09 00 00 00 00", 12, 2))
+        );
+    }
+
+    fn encode_v2(records: &[TxRecord]) -> Vec<u8> {
+        let mut writer =
+            BinWriter::with_version(Vec::new(), BinVersion::V2).created_at(1633036800000);
+        writer.write_all(records).unwrap();
+        writer.into_inner().unwrap()
+    }
+
+    #[test]
+    fn test_bin_v2_round_trip() {
+        let records = BinReader::new(EXAMPLE).read_all().unwrap();
+        let data = encode_v2(&records);
+        assert!(data.starts_with(&FILE_MAGIC));
+        assert_eq!(
+            data.len(),
+            EXAMPLE.len() + FILE_HEADER_SIZE + CRC_SIZE * records.len()
+        );
+
+        let mut reader = BinReader::new(data.as_slice());
+        assert_eq!(reader.read_all().unwrap(), records);
+        assert_eq!(reader.version(), BinVersion::V2);
+        assert_eq!(reader.created_at(), Some(1633036800000));
+
+        let mut reader = BinReader::new(EXAMPLE);
+        reader.read_all().unwrap();
+        assert_eq!(reader.version(), BinVersion::V1);
+        assert_eq!(reader.created_at(), None);
+
+        // Пустой файл версии 2 - только заголовок
+        let empty = encode_v2(&[]);
+        assert_eq!(empty.len(), FILE_HEADER_SIZE);
+        let mut reader = BinReader::new(empty.as_slice());
+        assert!(reader.read_all().unwrap().is_empty());
+        assert_eq!(reader.version(), BinVersion::V2);
+    }
+
+    #[test]
+    fn test_bin_v2_checksum_errors() {
+        let records = vec![
+            described(1, "first"),
+            described(2, "second"),
+            described(3, "third"),
+        ];
+        let data = encode_v2(&records);
+        let second = FILE_HEADER_SIZE + encode_bin(&records[..1]).len() + CRC_SIZE;
+
+        // Бит в описании второй записи: структура цела, выдаёт только CRC32
+        let mut broken = data.clone();
+        broken[second + HEADER_SIZE + BODY_FIXED_SIZE] ^= 0x20;
+        let err = BinReader::new(broken.as_slice()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::ChecksumMismatch { expected, actual } if expected != actual);
+        assert_eq!(err.field, Some("CRC32"));
+        let trailer = second + HEADER_SIZE + BODY_FIXED_SIZE + "second".len();
+        assert_eq!(err.offset(), Some(trailer as u64));
+
+        let mut reader = BinReader::with_resync(broken.as_slice());
+        let ids: Vec<u64> = reader.read_all().unwrap().iter().map(|r| r.tx_id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        assert_eq!(reader.skipped().len(), 1);
+        assert_eq!(reader.skipped()[0].offset, second as u64);
+
+        // Неизвестная версия - ошибка и в нестрогом режиме
+        let mut broken = data.clone();
+        broken[7] = 3;
+        let err = BinReader::with_resync(broken.as_slice())
+            .read_all()
+            .unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == "3");
+        assert_eq!(err.field, Some("VERSION"));
+        assert_eq!(err.offset(), Some(4));
+
+        let err = BinReader::new(&data[..10]).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_bin_resync_skips_damaged_record() {
+        let records = vec![
+            described(1, "first"),
+            described(2, "second"),
+            described(3, "third"),
+        ];
+        let mut data = encode_bin(&records);
+        let second = encode_bin(&records[..1]).len();
+
+        // Портим RECORD_SIZE второй записи
+        data[second + 7] = 0xFF;
+        let mut reader = BinReader::with_resync(data.as_slice());
+        let mut decoded = Vec::new();
+        while let Some(record) = reader.read_record().unwrap() {
+            decoded.push(record);
+        }
+
+        assert_eq!(decoded, vec![records[0].clone(), records[2].clone()]);
+        assert_eq!(reader.skipped().len(), 1);
+        assert_eq!(reader.skipped()[0].offset, second as u64);
+    }
+
+    #[test]
+    fn test_bin_resync_garbage_and_truncation() {
+        let records = vec![described(1, "first"), described(2, "second")];
+        let mut data = b"garbageYP".to_vec();
+        data.extend(encode_bin(&records));
+        data.extend_from_slice(b"YPBN\x00\x00");
+
+        let mut records_iter = BinReader::with_resync(data.as_slice()).records();
+        let decoded: Vec<TxRecord> = records_iter.by_ref().map(Result::unwrap).collect();
+        let reader = records_iter.into_inner();
+
+        assert_eq!(decoded, records);
+        let offsets: Vec<u64> = reader.skipped().iter().map(|s| s.offset).collect();
+        assert_eq!(offsets, vec![0, data.len() as u64 - 6]);
+        assert_matches!(
+            reader.skipped()[1].error.kind,
+            ParseErrorKind::UnexpectedEof
+        );
+    }
+}
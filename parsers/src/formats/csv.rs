@@ -0,0 +1,387 @@
+use super::{RecordReader, RecordWriter, check_line_description};
+use crate::{
+    errors::{Line, ParseError, ParseErrorKind, ParseMode},
+    record::{FIELDS, TxRecord},
+};
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+};
+
+/// Обязательный заголовок CSV-файла
+pub const HEADER: &str =
+    "TX_ID,TX_TYPE,FROM_USER_ID,TO_USER_ID,AMOUNT,TIMESTAMP,STATUS,DESCRIPTION";
+
+/// Чтение записей формата YPBankCsv
+///
+/// В нестрогом режиме неверные строки пропускаются, неверный заголовок - всегда ошибка.
+pub struct CsvReader<R: BufRead> {
+    inner: R,
+    buf: Vec<u8>,
+    line: usize,
+    mode: ParseMode,
+    errors: Vec<ParseError>,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_mode(inner, ParseMode::Strict)
+    }
+
+    pub fn with_mode(inner: R, mode: ParseMode) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            line: 0,
+            mode,
+            errors: Vec::new(),
+        }
+    }
+
+    fn next_line(&mut self) -> Result<bool, ParseError> {
+        self.buf.clear();
+        if self.inner.read_until(b'\n', &mut self.buf)? == 0 {
+            return Ok(false);
+        }
+        self.line += 1;
+        Ok(true)
+    }
+}
+
+impl<R: BufRead> RecordReader for CsvReader<R> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        loop {
+            let is_header = self.line == 0;
+            if !self.next_line()? {
+                if is_header {
+                    return Err(ParseError::new(ParseErrorKind::InvalidHeader).at_line(1, 1));
+                }
+                return Ok(None);
+            }
+            let decoded = if is_header {
+                Line::decode(self.line, &self.buf)
+            } else {
+                Line::decode_with(self.line, &self.buf, utf8_field)
+            };
+            let line = match decoded {
+                Ok(line) => line,
+                Err(err) if !is_header && self.mode == ParseMode::Lenient => {
+                    self.errors.push(err);
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+
+            if is_header {
+                if line.text != HEADER {
+                    return Err(line.error(ParseErrorKind::InvalidHeader, line.text));
+                }
+                continue;
+            }
+            if line.text.trim().is_empty() {
+                continue;
+            }
+            match parse_line(&line) {
+                Err(err) if self.mode == ParseMode::Lenient => self.errors.push(err),
+                result => return result.map(Some),
+            }
+        }
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+/// Поле строки с неверным UTF-8 на смещении `at`, поля делятся как в [parse_line]
+fn utf8_field(text: &str, at: usize) -> Option<(&'static str, Range<usize>)> {
+    let mut start = 0;
+    for (field, part) in FIELDS.iter().zip(text.splitn(FIELDS.len(), ',')) {
+        let end = start + part.len();
+        if at < end {
+            return Some((field, start..end));
+        }
+        start = end + 1;
+    }
+    None
+}
+
+fn parse_line(line: &Line) -> Result<TxRecord, ParseError> {
+    let parts: Vec<&str> = line.text.splitn(8, ',').collect();
+    let [
+        tx_id,
+        tx_type,
+        from,
+        to,
+        amount,
+        timestamp,
+        status,
+        description,
+    ] = parts[..]
+    else {
+        return Err(line.error(ParseErrorKind::InvalidLine, line.text));
+    };
+
+    let unquoted = description
+        .strip_prefix('"')
+        .and_then(|d| d.strip_suffix('"'))
+        .ok_or_else(|| {
+            line.error(
+                ParseErrorKind::InvalidValue(description.to_string()),
+                description,
+            )
+            .with_field("DESCRIPTION")
+        })?;
+
+    Ok(TxRecord {
+        tx_id: line.parse("TX_ID", tx_id)?,
+        tx_type: line.parse("TX_TYPE", tx_type)?,
+        from_user_id: line.parse("FROM_USER_ID", from)?,
+        to_user_id: line.parse("TO_USER_ID", to)?,
+        amount: line.parse("AMOUNT", amount)?,
+        timestamp: line.parse("TIMESTAMP", timestamp)?,
+        status: line.parse("STATUS", status)?,
+        description: unquoted.replace("\"\"", "\""),
+    })
+}
+
+/// Запись в формате YPBankCsv. Заголовок пишется перед первой записью.
+/// Запись с переводом строки в DESCRIPTION отклоняется
+pub struct CsvWriter<W: Write> {
+    inner: W,
+    header_written: bool,
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            header_written: false,
+        }
+    }
+
+    /// Дописывание в файл, где заголовок уже есть: он не пишется
+    pub fn appending(mut self) -> Self {
+        self.header_written = true;
+        self
+    }
+
+    /// Сбрасывает буфер и возвращает внутренний writer
+    pub fn into_inner(mut self) -> Result<W, ParseError> {
+        self.finish()?;
+        Ok(self.inner)
+    }
+
+    fn write_header(&mut self) -> Result<(), ParseError> {
+        if !self.header_written {
+            writeln!(self.inner, "{}", HEADER)?;
+            self.header_written = true;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> RecordWriter for CsvWriter<W> {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        check_line_description(record)?;
+        self.write_header()?;
+        writeln!(
+            self.inner,
+            "{},{},{},{},{},{},{},\"{}\"",
+            record.tx_id,
+            record.tx_type,
+            record.from_user_id,
+            record.to_user_id,
+            record.amount,
+            record.timestamp,
+            record.status,
+            record.description.replace('"', "\"\"")
+        )?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.write_header()?;
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::{Position, Snippet},
+        record::{TxStatus, TxType},
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &str = include_str!("../../../data/records_example.csv");
+
+    #[test]
+    fn test_csv_read_spec_example() {
+        let data = format!(
+            "{}\n{}\n\n{}\n{}\n",
+            HEADER,
+            "1001,DEPOSIT,0,501,50000,1672531200000,SUCCESS,\"Initial account funding\"",
+            "1002,TRANSFER,501,502,15000,1672534800000,FAILURE,\"Payment for services, invoice #123\"",
+            "1003,WITHDRAWAL,502,0,1000,1672538400000,PENDING,\"ATM withdrawal\""
+        );
+        let records = CsvReader::new(data.as_bytes()).read_all().unwrap();
+
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[1].tx_type, TxType::Transfer);
+        assert_eq!(records[1].status, TxStatus::Failure);
+        assert_eq!(records[1].description, "Payment for services, invoice #123");
+        assert_eq!(records[2].from_user_id, 502);
+    }
+
+    #[test]
+    fn test_csv_read_invalid_header() {
+        let data = "TX_ID,TX_TYPE\n";
+        let err = CsvReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidHeader);
+        assert_eq!(err.line(), Some(1));
+
+        let err = CsvReader::new("".as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidHeader);
+
+        // Неверный UTF-8 портит только свою строку
+        let mut data = format!("{}\n1,DEPOSIT,0,1,10,0,SUCCESS,\"ok\"\n", HEADER).into_bytes();
+        data.extend_from_slice(b"2,DEPOSIT,0,1,10,0,SUCCESS,\"\xD0\xB0\xFF\"\n");
+        data.extend_from_slice(b"3,DEPOSIT,0,1,10,0,SUCCESS,\"ok\"\n");
+        let report = CsvReader::with_mode(data.as_slice(), ParseMode::Lenient)
+            .read_report()
+            .unwrap();
+        let ids: Vec<u64> = report.data.iter().map(|r| r.tx_id).collect();
+        assert_eq!(ids, vec![1, 3]);
+        let positions: Vec<_> = report.errors.iter().map(|e| e.position).collect();
+        assert_eq!(
+            positions,
+            vec![Some(Position::Line {
+                line: 3,
+                column: 30
+            })]
+        );
+        assert_eq!(report.errors[0].field, Some("DESCRIPTION"));
+        assert_matches!(report.errors[0].kind, ParseErrorKind::InvalidValue(ref v) if v == "\"а\u{FFFD}\"");
+        let err = CsvReader::new(data.as_slice()).read_all().unwrap_err();
+        assert_eq!(err.line(), Some(3));
+    }
+
+    #[test]
+    fn test_csv_read_invalid_field() {
+        let data = format!("{}\n1,DEPOSIT,0,1,abc,0,SUCCESS,\"\"\n", HEADER);
+        let err = CsvReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == "abc");
+        assert_eq!(err.field, Some("AMOUNT"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 2,
+                column: 15
+            })
+        );
+        assert_eq!(
+            err.snippet,
+            Some(Snippet::new("1,DEPOSIT,0,1,abc,0,SUCCESS,\"\"", 14, 3))
+        );
+
+        let data = format!("{}\n1,DEPOSIT,0,1,10,0,SUCCESS,no quotes\n", HEADER);
+        let err = CsvReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_eq!(err.field, Some("DESCRIPTION"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 2,
+                column: 28
+            })
+        );
+
+        let data = format!("{}\n1,DEPOSIT,0\n", HEADER);
+        let err = CsvReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidLine);
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn test_csv_read_lenient() {
+        let data = format!(
+            "{}\n1,DEPOSIT,0,1,abc,0,SUCCESS,\"\"\n2,DEPOSIT,0,1,10,0,SUCCESS,\"ok\"\n3,DEPOSIT\n",
+            HEADER
+        );
+        let report = CsvReader::with_mode(data.as_bytes(), ParseMode::Lenient)
+            .read_report()
+            .unwrap();
+
+        assert_eq!(report.data.len(), 1);
+        assert_eq!(report.data[0].tx_id, 2);
+        let lines: Vec<Option<usize>> = report.errors.iter().map(|e| e.line()).collect();
+        assert_eq!(lines, vec![Some(2), Some(4)]);
+
+        let err = CsvReader::with_mode("TX_ID\n".as_bytes(), ParseMode::Lenient)
+            .read_report()
+            .unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidHeader);
+    }
+
+    #[test]
+    fn test_csv_example_round_trip() {
+        let records = CsvReader::new(EXAMPLE.as_bytes()).read_all().unwrap();
+        assert_eq!(records.len(), 1000);
+
+        let mut writer = CsvWriter::new(Vec::new());
+        writer.write_all(&records).unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), EXAMPLE);
+    }
+
+    #[test]
+    fn test_csv_description_quotes_round_trip() {
+        let record = TxRecord {
+            tx_id: 1,
+            tx_type: TxType::Deposit,
+            from_user_id: 0,
+            to_user_id: 2,
+            amount: 100,
+            timestamp: 1,
+            status: TxStatus::Pending,
+            description: "say \"hi\", ok".into(),
+        };
+        let mut writer = CsvWriter::new(Vec::new());
+        writer.write_record(&record).unwrap();
+        let output = writer.into_inner().unwrap();
+
+        let records = CsvReader::new(output.as_slice()).read_all().unwrap();
+        assert_eq!(records, vec![record]);
+    }
+
+    #[test]
+    fn test_csv_write_rejects_line_breaks() {
+        let mut record = TxRecord {
+            tx_id: 1,
+            tx_type: TxType::Deposit,
+            from_user_id: 0,
+            to_user_id: 2,
+            amount: 100,
+            timestamp: 1,
+            status: TxStatus::Pending,
+            description: "first\nsecond".into(),
+        };
+        let mut writer = CsvWriter::new(Vec::new());
+        let err = writer.write_record(&record).unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == "first\nsecond");
+        assert_eq!(err.field, Some("DESCRIPTION"));
+        record.description = "a\rb".into();
+        assert!(writer.write_record(&record).is_err());
+
+        // Отклонённые записи не попадают в файл
+        record.description = "ok".into();
+        writer.write_record(&record).unwrap();
+        let output = writer.into_inner().unwrap();
+        assert_eq!(
+            CsvReader::new(output.as_slice()).read_all().unwrap(),
+            vec![record]
+        );
+    }
+}
@@ -0,0 +1,219 @@
+use super::{
+    Format,
+    binary::{FILE_MAGIC, MAGIC},
+    csv::HEADER,
+};
+use crate::record::FIELDS;
+use std::{
+    fmt::Display,
+    io::{BufRead, Chain, Cursor, Read},
+};
+
+/// Сколько байт начала потока читать самое большее
+const DETECT_LIMIT: usize = 8 * 1024;
+
+/// Поток после [detect_format]: прочитанное начало, затем остаток
+pub type Detected<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// Ошибки определения формата
+#[derive(Debug, PartialEq, Eq)]
+pub enum DetectError {
+    /// Пустой вход
+    Empty,
+
+    /// Начало файла подходит под несколько форматов
+    Ambiguous(Vec<Format>),
+
+    /// Начало файла не подходит ни под один формат
+    Unknown(String),
+
+    Io(String),
+}
+
+impl Display for DetectError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DetectError::Empty => write!(f, "пустой вход, формат не определить"),
+            DetectError::Ambiguous(formats) => {
+                let formats: Vec<&str> = formats.iter().map(|f| f.as_str()).collect();
+                write!(
+                    f,
+                    "формат неоднозначен ({}), укажите его явно",
+                    formats.join(", ")
+                )
+            }
+            DetectError::Unknown(start) => write!(f, "неизвестный формат, начало: {:?}", start),
+            DetectError::Io(err) => write!(f, "ошибка ввода-вывода: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for DetectError {}
+
+/// Строка вида `KEY: value` с ключом из спецификации
+fn is_text_field(line: &str) -> bool {
+    line.split_once(':')
+        .is_some_and(|(key, _)| FIELDS.contains(&key.trim()))
+}
+
+/// Определяет формат по началу потока
+///
+/// - `YPBN` или `YPBF` (версия 2) в начале - бинарный формат;
+/// - первая строка - заголовок CSV - CSV;
+/// - первый непробельный символ `{` - JSON Lines;
+/// - строки `KEY: value` или комментарии `#` - текстовый формат.
+///
+/// Поток может отдавать данные мелкими частями, поэтому начало читается, пока формат
+/// не станет ясен, до конца потока или до 8 КиБ. Прочитанное возвращается вместе
+/// с остатком потока: [Detected] читается с самого начала.
+pub fn detect_format<R: BufRead>(mut reader: R) -> Result<(Format, Detected<R>), DetectError> {
+    let mut start = Vec::new();
+    let format = loop {
+        let chunk = reader
+            .fill_buf()
+            .map_err(|err| DetectError::Io(err.to_string()))?;
+        let end = chunk.is_empty();
+        let taken = chunk.len().min(DETECT_LIMIT - start.len());
+        start.extend_from_slice(&chunk[..taken]);
+        reader.consume(taken);
+        if let Some(result) = classify(&start, end || start.len() == DETECT_LIMIT) {
+            break result?;
+        }
+    };
+    Ok((format, Cursor::new(start).chain(reader)))
+}
+
+/// Формат по началу потока, `None` - для решения нужно больше байт.
+/// Без `complete` последняя строка может быть оборвана и не учитывается
+fn classify(start: &[u8], complete: bool) -> Option<Result<Format, DetectError>> {
+    if start.is_empty() {
+        return complete.then_some(Err(DetectError::Empty));
+    }
+    if start.starts_with(&MAGIC) || start.starts_with(&FILE_MAGIC) {
+        return Some(Ok(Format::Bin));
+    }
+    if !complete && (MAGIC.starts_with(start) || FILE_MAGIC.starts_with(start)) {
+        return None;
+    }
+
+    let text = String::from_utf8_lossy(start);
+    if text.trim_start().starts_with('{') {
+        return Some(Ok(Format::Json));
+    }
+    let lines = match complete {
+        true => &text[..],
+        false => &text[..text.rfind('\n').map_or(0, |end| end + 1)],
+    };
+    if lines.lines().next() == Some(HEADER) {
+        return Some(Ok(Format::Csv));
+    }
+
+    // Комментарии допустимы только в текстовом формате,
+    // но за ними может оказаться заголовок CSV
+    let unknown = || DetectError::Unknown(text.chars().take(32).collect());
+    let mut has_comments = false;
+    for line in lines.lines().map(str::trim) {
+        if line.starts_with('#') {
+            has_comments = true;
+        } else if is_text_field(line) {
+            return Some(Ok(Format::Text));
+        } else if line == HEADER && has_comments {
+            return Some(Err(DetectError::Ambiguous(vec![Format::Text, Format::Csv])));
+        } else if !line.is_empty() {
+            return Some(Err(unknown()));
+        }
+    }
+    match (complete, has_comments) {
+        (false, _) => None,
+        (true, true) => Some(Ok(Format::Text)),
+        (true, false) => Some(Err(unknown())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::RecordReader;
+    use std::io::BufReader;
+
+    const EXAMPLE_CSV: &[u8] = include_bytes!("../../../data/records_example.csv");
+    const EXAMPLE_TEXT: &[u8] = include_bytes!("../../../data/records_example.txt");
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    fn detect(data: &[u8]) -> Result<Format, DetectError> {
+        detect_format(data).map(|(format, _)| format)
+    }
+
+    #[test]
+    fn test_detect_examples() {
+        assert_eq!(detect(EXAMPLE_CSV), Ok(Format::Csv));
+        assert_eq!(detect(EXAMPLE_TEXT), Ok(Format::Text));
+        assert_eq!(detect(EXAMPLE_BIN), Ok(Format::Bin));
+        assert_eq!(detect(b"YPBF\0\0\0\x02"), Ok(Format::Bin));
+    }
+
+    #[test]
+    fn test_detect_does_not_consume() {
+        let input = BufReader::new(EXAMPLE_TEXT);
+        let (format, input) = detect_format(input).unwrap();
+        assert_eq!(format.reader(input).read_all().unwrap().len(), 1000);
+    }
+
+    #[test]
+    fn test_detect_reads_small_chunks() {
+        // Буфер в один байт: каждый fill_buf отдаёт по байту, как медленный канал
+        for (data, expected) in [
+            (EXAMPLE_CSV, Format::Csv),
+            (EXAMPLE_TEXT, Format::Text),
+            (EXAMPLE_BIN, Format::Bin),
+        ] {
+            let (format, input) = detect_format(BufReader::with_capacity(1, data)).unwrap();
+            assert_eq!(format, expected);
+            assert_eq!(format.reader(input).read_all().unwrap().len(), 1000);
+        }
+
+        let input = BufReader::with_capacity(1, "# export\n\nTX_ID: 1\n".as_bytes());
+        assert_eq!(detect_format(input).unwrap().0, Format::Text);
+        let input = BufReader::with_capacity(1, "YP".as_bytes());
+        assert!(matches!(detect_format(input), Err(DetectError::Unknown(_))));
+    }
+
+    #[test]
+    fn test_detect_text_without_comments() {
+        assert_eq!(detect(b"\nAMOUNT: 10\nTX_ID: 1\n"), Ok(Format::Text));
+        assert_eq!(
+            detect("# только комментарий\n".as_bytes()),
+            Ok(Format::Text)
+        );
+    }
+
+    #[test]
+    fn test_detect_json() {
+        assert_eq!(detect(b"\n  {\"tx_id\": 1}\n"), Ok(Format::Json));
+
+        // Формат определён, ошибки содержимого находит читатель
+        let (format, input) = detect_format("{\"TX_ID\": 1}".as_bytes()).unwrap();
+        assert_eq!(format, Format::Json);
+        assert!(Format::Json.reader(input).read_all().is_err());
+    }
+
+    #[test]
+    fn test_detect_errors() {
+        assert_eq!(detect(b""), Err(DetectError::Empty));
+
+        let input = format!("# export\n{}\n", HEADER);
+        assert_eq!(
+            detect(input.as_bytes()),
+            Err(DetectError::Ambiguous(vec![Format::Text, Format::Csv]))
+        );
+
+        assert!(matches!(
+            detect(b"TX_ID,TX_TYPE\n1,DEPOSIT\n"),
+            Err(DetectError::Unknown(_))
+        ));
+        assert!(matches!(
+            detect(b"# comment\ngarbage"),
+            Err(DetectError::Unknown(_))
+        ));
+    }
+}
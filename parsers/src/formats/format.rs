@@ -0,0 +1,208 @@
+use super::{
+    RecordReader, RecordWriter,
+    binary::{BinReader, BinWriter},
+    csv::{CsvReader, CsvWriter},
+    json::{JsonReader, JsonWriter},
+    text::{TextReader, TextWriter},
+};
+use crate::{compress::Compression, errors::ParseMode};
+use std::{
+    fmt::Display,
+    io::{BufRead, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// Формат файла YPBank, выбираемый во время выполнения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    Csv,
+    Text,
+    Bin,
+    Json,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Csv, Format::Text, Format::Bin, Format::Json];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Text => "text",
+            Format::Bin => "bin",
+            Format::Json => "json",
+        }
+    }
+
+    /// Формат по расширению файла, расширение сжатия (`a.csv.gz`) пропускается
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let mut path = path.as_ref();
+        if Compression::from_path(path) != Compression::None {
+            path = Path::new(path.file_stem()?);
+        }
+        match path.extension()?.to_str()? {
+            "csv" => Some(Format::Csv),
+            "txt" => Some(Format::Text),
+            "bin" => Some(Format::Bin),
+            "json" | "jsonl" | "ndjson" => Some(Format::Json),
+            _ => None,
+        }
+    }
+
+    /// Строгий читатель этого формата
+    pub fn reader<'a, R: BufRead + 'a>(self, inner: R) -> Box<dyn RecordReader + 'a> {
+        self.reader_with(inner, ParseMode::Strict)
+    }
+
+    /// Читатель этого формата в режиме `mode`
+    pub fn reader_with<'a, R: BufRead + 'a>(
+        self,
+        inner: R,
+        mode: ParseMode,
+    ) -> Box<dyn RecordReader + 'a> {
+        match self {
+            Format::Csv => Box::new(CsvReader::with_mode(inner, mode)),
+            Format::Text => Box::new(TextReader::with_mode(inner, mode)),
+            Format::Bin => Box::new(BinReader::with_mode(inner, mode)),
+            Format::Json => Box::new(JsonReader::with_mode(inner, mode)),
+        }
+    }
+
+    /// Писатель этого формата
+    pub fn writer<'a, W: Write + 'a>(self, inner: W) -> Box<dyn RecordWriter + 'a> {
+        match self {
+            Format::Csv => Box::new(CsvWriter::new(inner)),
+            Format::Text => Box::new(TextWriter::new(inner)),
+            Format::Bin => Box::new(BinWriter::new(inner)),
+            Format::Json => Box::new(JsonWriter::new(inner)),
+        }
+    }
+}
+
+impl Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(Format::Csv),
+            "text" | "txt" => Ok(Format::Text),
+            "bin" => Ok(Format::Bin),
+            "json" | "jsonl" | "ndjson" => Ok(Format::Json),
+            _ => Err(format!("неизвестный формат: {}", value)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::copy_records;
+
+    const EXAMPLE_CSV: &[u8] = include_bytes!("../../../data/records_example.csv");
+    const EXAMPLE_TEXT: &[u8] = include_bytes!("../../../data/records_example.txt");
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    /// Обобщённый код, которому не важен формат
+    fn count_records(reader: &mut dyn RecordReader) -> usize {
+        reader.read_all().unwrap().len()
+    }
+
+    #[test]
+    fn test_format_from_str() {
+        assert_eq!("csv".parse(), Ok(Format::Csv));
+        assert_eq!("txt".parse(), Ok(Format::Text));
+        assert_eq!("bin".parse(), Ok(Format::Bin));
+        assert_eq!("ndjson".parse(), Ok(Format::Json));
+        assert!("xml".parse::<Format>().is_err());
+
+        assert_eq!(Format::from_path("data/a.txt"), Some(Format::Text));
+        assert_eq!(Format::from_path("a.bin"), Some(Format::Bin));
+        assert_eq!(Format::from_path("a.jsonl.gz"), Some(Format::Json));
+        assert_eq!(Format::from_path("a"), None);
+        assert_eq!(Format::from_path("data/a.bin.zst"), Some(Format::Bin));
+        assert_eq!(Format::from_path("a.gz"), None);
+    }
+
+    #[test]
+    fn test_format_boxed_readers() {
+        for (format, data) in [
+            (Format::Csv, EXAMPLE_CSV),
+            (Format::Text, EXAMPLE_TEXT),
+            (Format::Bin, EXAMPLE_BIN),
+        ] {
+            assert_eq!(count_records(&mut format.reader(data)), 1000);
+        }
+
+        let csv = Format::Csv.reader(EXAMPLE_CSV).read_all().unwrap();
+        let text = Format::Text.reader(EXAMPLE_TEXT).read_all().unwrap();
+        assert_eq!(csv, text);
+    }
+
+    #[test]
+    fn test_format_boxed_round_trip() {
+        let records = Format::Csv.reader(EXAMPLE_CSV).read_all().unwrap();
+
+        for format in Format::ALL {
+            let mut output = Vec::new();
+            let mut writer = format.writer(&mut output);
+            writer.write_all(&records).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+
+            let parsed = format.reader(output.as_slice()).read_all().unwrap();
+            assert_eq!(parsed, records, "{}", format);
+        }
+    }
+
+    #[test]
+    fn test_copy_records_between_formats() {
+        let mut output = Vec::new();
+        let mut writer = Format::Text.writer(&mut output);
+        let count = copy_records(&mut Format::Csv.reader(EXAMPLE_CSV), &mut writer).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+
+        assert_eq!(count, 1000);
+        let text = Format::Text.reader(output.as_slice()).read_all().unwrap();
+        let csv = Format::Csv.reader(EXAMPLE_CSV).read_all().unwrap();
+        assert_eq!(text, csv);
+    }
+
+    #[test]
+    fn test_records_iterator() {
+        for (format, data) in [
+            (Format::Csv, EXAMPLE_CSV),
+            (Format::Text, EXAMPLE_TEXT),
+            (Format::Bin, EXAMPLE_BIN),
+        ] {
+            let ids: Vec<u64> = format
+                .reader(data)
+                .records()
+                .map(|r| r.unwrap().tx_id)
+                .collect();
+            assert_eq!(ids.len(), 1000);
+            assert_eq!(ids[999], 1000000000000999);
+        }
+
+        // После ошибки итератор заканчивается
+        let cut = EXAMPLE_CSV
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == b'\n')
+            .nth(2)
+            .unwrap()
+            .0
+            + 1;
+        let data = [&EXAMPLE_CSV[..cut], b"broken line\n", &EXAMPLE_CSV[cut..]].concat();
+        let results: Vec<_> = Format::Csv.reader(data.as_slice()).records().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[1].is_ok());
+        assert!(results[2].is_err());
+    }
+}
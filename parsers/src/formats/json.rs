@@ -0,0 +1,352 @@
+//! Формат YPBankJson: JSON Lines, одна запись - один объект JSON на строке
+//!
+//! ```text
+//! {"tx_id":1000000000000000,"tx_type":"DEPOSIT","from_user_id":0,"to_user_id":9223372036854775807,"amount":100,"timestamp":1633036860000,"status":"FAILURE","description":"Record number 1"}
+//! ```
+//!
+//! Схема объекта - поля [TxRecord], все обязательны, других полей быть не должно:
+//!
+//! | Ключ           | Тип                                          |
+//! |----------------|----------------------------------------------|
+//! | `tx_id`        | целое `0..=2^64-1`                           |
+//! | `tx_type`      | строка `DEPOSIT`, `TRANSFER` или `WITHDRAWAL` |
+//! | `from_user_id` | целое `0..=2^64-1`                           |
+//! | `to_user_id`   | целое `0..=2^64-1`                           |
+//! | `amount`       | целое `-2^63..=2^63-1`                       |
+//! | `timestamp`    | целое, миллисекунды Unix                     |
+//! | `status`       | строка `SUCCESS`, `FAILURE` или `PENDING`    |
+//! | `description`  | строка                                       |
+//!
+//! Целые пишутся числами JSON. Числа больше 2^53 теряют точность в JavaScript,
+//! поэтому при чтении целое можно передать и строкой из цифр: `"tx_id":"1000000000000000"`.
+//! Пустые строки пропускаются, порядок ключей не важен, при повторе ключа берётся последнее значение.
+
+use super::{RecordReader, RecordWriter};
+use crate::{
+    errors::{Line, ParseError, ParseErrorKind, ParseMode},
+    record::{FIELDS, TxRecord},
+};
+use serde_json::{Map, Value};
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+    str::FromStr,
+};
+
+/// Ключи объекта в порядке [FIELDS]
+pub const KEYS: [&str; 8] = [
+    "tx_id",
+    "tx_type",
+    "from_user_id",
+    "to_user_id",
+    "amount",
+    "timestamp",
+    "status",
+    "description",
+];
+
+/// Чтение записей формата YPBankJson
+///
+/// В нестрогом режиме неверные строки пропускаются.
+pub struct JsonReader<R: BufRead> {
+    inner: R,
+    buf: Vec<u8>,
+    line: usize,
+    mode: ParseMode,
+    errors: Vec<ParseError>,
+}
+
+impl<R: BufRead> JsonReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_mode(inner, ParseMode::Strict)
+    }
+
+    pub fn with_mode(inner: R, mode: ParseMode) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            line: 0,
+            mode,
+            errors: Vec::new(),
+        }
+    }
+}
+
+impl<R: BufRead> RecordReader for JsonReader<R> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        loop {
+            self.buf.clear();
+            if self.inner.read_until(b'\n', &mut self.buf)? == 0 {
+                return Ok(None);
+            }
+            self.line += 1;
+            let decoded = Line::decode_with(self.line, &self.buf, utf8_field);
+            let result = decoded.and_then(|line| {
+                if line.text.trim().is_empty() {
+                    Ok(None)
+                } else {
+                    parse_line(&line).map(Some)
+                }
+            });
+            match result {
+                Ok(None) => continue,
+                Err(err) if self.mode == ParseMode::Lenient => self.errors.push(err),
+                result => return result,
+            }
+        }
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+/// Поле объекта с неверным UTF-8 на смещении `at` - значение последнего ключа перед ним
+fn utf8_field(text: &str, at: usize) -> Option<(&'static str, Range<usize>)> {
+    let (index, key_end) = KEYS
+        .iter()
+        .enumerate()
+        .filter_map(|(index, key)| {
+            let quoted = format!("\"{}\"", key);
+            text[..at]
+                .rfind(&quoted)
+                .map(|start| (index, start + quoted.len()))
+        })
+        .max_by_key(|&(_, end)| end)?;
+    let value = text[key_end..].trim_start().strip_prefix(':')?.trim_start();
+    let start = text.len() - value.len();
+    let end = if value.starts_with('"') {
+        // Строка кончается кавычкой без `\` перед ней
+        let mut escaped = false;
+        let close = text[at..].find(|c| {
+            let close = c == '"' && !escaped;
+            escaped = c == '\\' && !escaped;
+            close
+        });
+        close.map(|i| at + i + 1)
+    } else {
+        text[at..].find([',', '}']).map(|i| at + i)
+    };
+    (start <= at).then(|| (FIELDS[index], start..end.unwrap_or(text.len())))
+}
+
+fn parse_line(line: &Line) -> Result<TxRecord, ParseError> {
+    let object: Map<String, Value> = serde_json::from_str(line.text).map_err(|err| {
+        // serde_json считает колонки в байтах, с 1
+        let mut start = err.column().saturating_sub(1).min(line.text.len());
+        while !line.text.is_char_boundary(start) {
+            start -= 1;
+        }
+        let len = line.text[start..].chars().next().map_or(0, char::len_utf8);
+        line.error(ParseErrorKind::InvalidLine, &line.text[start..start + len])
+    })?;
+
+    if let Some(key) = object.keys().find(|key| !KEYS.contains(&key.as_str())) {
+        return Err(line.error(
+            ParseErrorKind::UnknownField(key.clone()),
+            key_part(line, key),
+        ));
+    }
+    let fields = Fields { line, object };
+    Ok(TxRecord {
+        tx_id: fields.parse(0)?,
+        tx_type: fields.parse(1)?,
+        from_user_id: fields.parse(2)?,
+        to_user_id: fields.parse(3)?,
+        amount: fields.parse(4)?,
+        timestamp: fields.parse(5)?,
+        status: fields.parse(6)?,
+        description: fields.description()?,
+    })
+}
+
+/// Ключ `key` в тексте строки для позиции ошибки, иначе вся строка
+fn key_part<'a>(line: &Line<'a>, key: &str) -> &'a str {
+    let quoted = format!("\"{}\"", key);
+    match line.text.find(&quoted) {
+        Some(start) => &line.text[start..start + quoted.len()],
+        None => line.text,
+    }
+}
+
+/// Поля разобранного объекта
+struct Fields<'a> {
+    line: &'a Line<'a>,
+    object: Map<String, Value>,
+}
+
+impl Fields<'_> {
+    fn value(&self, index: usize) -> Result<&Value, ParseError> {
+        self.object.get(KEYS[index]).ok_or_else(|| {
+            ParseError::new(ParseErrorKind::MissingField)
+                .at_line(self.line.number, 1)
+                .with_field(FIELDS[index])
+        })
+    }
+
+    fn invalid(&self, index: usize, value: &Value) -> ParseError {
+        self.line
+            .error(
+                ParseErrorKind::InvalidValue(value.to_string()),
+                key_part(self.line, KEYS[index]),
+            )
+            .with_field(FIELDS[index])
+    }
+
+    /// Число, строка из цифр или значение перечисления
+    fn parse<T: FromStr>(&self, index: usize) -> Result<T, ParseError> {
+        let value = self.value(index)?;
+        let parsed = match value {
+            Value::Number(number) => number.to_string().parse().ok(),
+            Value::String(text) => text.parse().ok(),
+            _ => None,
+        };
+        parsed.ok_or_else(|| self.invalid(index, value))
+    }
+
+    fn description(&self) -> Result<String, ParseError> {
+        match self.value(7)? {
+            Value::String(text) => Ok(text.clone()),
+            value => Err(self.invalid(7, value)),
+        }
+    }
+}
+
+/// Запись в формате YPBankJson, ключи - в порядке [KEYS]
+pub struct JsonWriter<W: Write> {
+    inner: W,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner }
+    }
+
+    /// Сбрасывает буфер и возвращает внутренний writer
+    pub fn into_inner(mut self) -> Result<W, ParseError> {
+        self.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> RecordWriter for JsonWriter<W> {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        writeln!(
+            self.inner,
+            "{{\"tx_id\":{},\"tx_type\":\"{}\",\"from_user_id\":{},\"to_user_id\":{},\
+             \"amount\":{},\"timestamp\":{},\"status\":\"{}\",\"description\":{}}}",
+            record.tx_id,
+            record.tx_type,
+            record.from_user_id,
+            record.to_user_id,
+            record.amount,
+            record.timestamp,
+            record.status,
+            Value::from(record.description.as_str())
+        )?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::Position,
+        formats::binary::BinReader,
+        record::{TxStatus, TxType},
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    const LINE: &str = r#"{"tx_id":1000000000000000,"tx_type":"DEPOSIT","from_user_id":0,"to_user_id":9223372036854775807,"amount":100,"timestamp":1633036860000,"status":"FAILURE","description":"\"Record number 1\""}"#;
+
+    #[test]
+    fn test_json_read_schema() {
+        let records = JsonReader::new(format!("{}\n\n", LINE).as_bytes())
+            .read_all()
+            .unwrap();
+        let first = BinReader::new(EXAMPLE_BIN).read_record().unwrap();
+        assert_eq!(records, vec![first.unwrap()]);
+
+        // Порядок ключей не важен, целые можно передать строкой
+        let input = r#"{"description":"a \"q\"\nb","status":"PENDING","timestamp":"1","amount":-5,"to_user_id":"18446744073709551615","from_user_id":2,"tx_type":"TRANSFER","tx_id":3}"#;
+        let record = JsonReader::new(input.as_bytes())
+            .read_record()
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.tx_type, TxType::Transfer);
+        assert_eq!(record.status, TxStatus::Pending);
+        assert_eq!(record.to_user_id, u64::MAX);
+        assert_eq!(record.amount, -5);
+        assert_eq!(record.description, "a \"q\"\nb");
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.write_all(&records).unwrap();
+        let written = writer.into_inner().unwrap();
+
+        assert_eq!(written.split(|&b| b == b'\n').next(), Some(LINE.as_bytes()));
+        let parsed = JsonReader::new(written.as_slice()).read_all().unwrap();
+        assert_eq!(parsed, records);
+    }
+
+    #[test]
+    fn test_json_errors() {
+        let read = |input: &str| JsonReader::new(input.as_bytes()).read_all().unwrap_err();
+
+        let err = read(&format!("{}\n{{\"tx_id\": 1,", LINE));
+        assert_matches!(err.kind, ParseErrorKind::InvalidLine);
+        assert_eq!(err.line(), Some(2));
+
+        let err = read(&LINE.replace("\"amount\":100", "\"amount\":1.5"));
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == "1.5");
+        assert_eq!(err.field, Some("AMOUNT"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 1,
+                column: 97
+            })
+        );
+
+        let err = read(&LINE.replace("\"FAILURE\"", "\"DONE\""));
+        assert_eq!(err.field, Some("STATUS"));
+        let err = read(&LINE.replace(r#""\"Record number 1\"""#, "null"));
+        assert_eq!(err.field, Some("DESCRIPTION"));
+        let err = read(&LINE.replace("\"tx_id\":1000000000000000,", ""));
+        assert_matches!(err.kind, ParseErrorKind::MissingField);
+        assert_eq!(err.field, Some("TX_ID"));
+        let err = read(&LINE.replace("\"tx_id\"", "\"TX_ID\""));
+        assert_matches!(err.kind, ParseErrorKind::UnknownField(ref k) if k == "TX_ID");
+        assert_matches!(read("[1, 2]").kind, ParseErrorKind::InvalidLine);
+
+        let input = format!("{}\n{{}}\nnot json\n{}\n", LINE, LINE);
+        let mut reader = JsonReader::with_mode(input.as_bytes(), ParseMode::Lenient);
+        let report = reader.read_report().unwrap();
+        assert_eq!(report.data.len(), 2);
+        let lines: Vec<_> = report.errors.iter().map(|e| e.line()).collect();
+        assert_eq!(lines, vec![Some(2), Some(3)]);
+
+        let mut input = LINE.replace("Record", "Rec\u{FFFD}ord").into_bytes();
+        let at = input
+            .windows(3)
+            .position(|w| w == "\u{FFFD}".as_bytes())
+            .unwrap();
+        input.splice(at..at + 3, [0xFF]);
+        let err = JsonReader::new(input.as_slice()).read_all().unwrap_err();
+        assert_eq!(err.field, Some("DESCRIPTION"));
+        let value = "\"\\\"Rec\u{FFFD}ord number 1\\\"\"";
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(ref v) if v == value);
+        assert_eq!(err.line(), Some(1));
+    }
+}
@@ -0,0 +1,359 @@
+//! Чтение YPBankBin из памяти без копирования
+//!
+//! Для аналитики по большим архивам: файл отображается в память целиком,
+//! записи отдаются как [TxRecordRef] с описанием, заимствованным из отображения.
+//! Владеющая [TxRecord] создаётся только по запросу.
+
+use super::{
+    RecordReader,
+    binary::{
+        BinVersion, FILE_HEADER_SIZE, FILE_MAGIC, HEADER_SIZE, MAGIC, SkippedRecord, bytes_error,
+        check_crc, check_file_header, check_header, decode_body,
+    },
+};
+use crate::{
+    errors::{ParseError, ParseErrorKind, ParseMode},
+    record::{TxRecord, TxRecordRef},
+};
+use memmap2::Mmap;
+use std::{fs::File, path::Path};
+
+/// Файл YPBankBin, отображённый в память
+#[derive(Debug)]
+pub struct BinMmap {
+    map: Mmap,
+}
+
+impl BinMmap {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, ParseError> {
+        Self::from_file(&File::open(path)?)
+    }
+
+    pub fn from_file(file: &File) -> Result<Self, ParseError> {
+        // SAFETY: отображение только читается. Если файл изменят или обрежут,
+        // пока он открыт, содержимое записей не определено - архивы считаются неизменяемыми.
+        let map = unsafe { Mmap::map(file)? };
+        Ok(Self { map })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// Записи файла в строгом режиме
+    pub fn records(&self) -> BinSliceReader<'_> {
+        BinSliceReader::new(&self.map)
+    }
+
+    pub fn records_with(&self, mode: ParseMode) -> BinSliceReader<'_> {
+        BinSliceReader::with_mode(&self.map, mode)
+    }
+}
+
+/// Чтение записей YPBankBin из среза байт без выделения памяти на запись
+///
+/// Итератор по [TxRecordRef]. В строгом режиме итерация заканчивается на первой ошибке,
+/// в нестрогом испорченная запись пропускается до следующего MAGIC, как в [BinReader](super::binary::BinReader).
+pub struct BinSliceReader<'a> {
+    data: &'a [u8],
+    /// Смещение следующей записи от начала среза
+    offset: usize,
+    /// Смещение последней прочитанной записи
+    record_offset: usize,
+    mode: ParseMode,
+    failed: bool,
+    skipped: Vec<SkippedRecord>,
+    /// Версия определяется по началу среза сразу, чтобы [BinSliceReader::seek] работал до чтения
+    version: BinVersion,
+    created_at: Option<u64>,
+}
+
+impl<'a> BinSliceReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_mode(data, ParseMode::Strict)
+    }
+
+    pub fn with_mode(data: &'a [u8], mode: ParseMode) -> Self {
+        Self {
+            data,
+            offset: 0,
+            record_offset: 0,
+            mode,
+            failed: false,
+            skipped: Vec::new(),
+            version: if data.starts_with(&FILE_MAGIC) {
+                BinVersion::V2
+            } else {
+                BinVersion::V1
+            },
+            created_at: None,
+        }
+    }
+
+    pub fn version(&self) -> BinVersion {
+        self.version
+    }
+
+    /// Время создания файла версии 2 в миллисекундах, известно после первого чтения
+    pub fn created_at(&self) -> Option<u64> {
+        self.created_at
+    }
+
+    /// Смещение следующей записи от начала среза
+    pub fn offset(&self) -> u64 {
+        self.offset as u64
+    }
+
+    /// Смещение последней прочитанной записи от начала среза
+    pub fn record_offset(&self) -> u64 {
+        self.record_offset as u64
+    }
+
+    /// Переходит к записи по смещению, например из индекса. Смещение за концом - конец данных
+    pub fn seek(&mut self, offset: u64) {
+        self.offset = usize::try_from(offset).map_or(self.data.len(), |o| o.min(self.data.len()));
+        self.failed = false;
+    }
+
+    /// Пропущенные в нестрогом режиме записи
+    pub fn skipped(&self) -> &[SkippedRecord] {
+        &self.skipped
+    }
+
+    /// Читает следующую запись, `None` - конец данных.
+    /// Ошибка в заголовке файла возвращается и в нестрогом режиме
+    pub fn read_ref(&mut self) -> Result<Option<TxRecordRef<'a>>, ParseError> {
+        if self.offset == 0 && self.version == BinVersion::V2 {
+            self.read_file_header()?;
+        }
+        loop {
+            match self.try_read_ref() {
+                Err(error) if self.mode == ParseMode::Lenient => {
+                    self.skipped.push(SkippedRecord {
+                        offset: self.offset as u64,
+                        error,
+                    });
+                    self.seek_magic();
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn read_file_header(&mut self) -> Result<(), ParseError> {
+        let Some(header) = self.data.get(..FILE_HEADER_SIZE) else {
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                0,
+                self.data,
+                0,
+                0,
+            ));
+        };
+        self.created_at = Some(check_file_header(header)?);
+        self.offset = FILE_HEADER_SIZE;
+        Ok(())
+    }
+
+    fn try_read_ref(&mut self) -> Result<Option<TxRecordRef<'a>>, ParseError> {
+        let data = self.data;
+        let pending = &data[self.offset..];
+        if pending.is_empty() {
+            return Ok(None);
+        }
+        let base = self.offset as u64;
+        if pending.len() < HEADER_SIZE {
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                base,
+                pending,
+                0,
+                0,
+            ));
+        }
+        let trailer = self.version.trailer_size();
+        let len = check_header(base, &pending[..HEADER_SIZE])? + trailer;
+        if pending.len() < len {
+            return Err(bytes_error(
+                ParseErrorKind::UnexpectedEof,
+                base,
+                &pending[..HEADER_SIZE],
+                0,
+                0,
+            ));
+        }
+        if self.version == BinVersion::V2 {
+            check_crc(base, &pending[..len])?;
+        }
+        let record = decode_body(
+            base + HEADER_SIZE as u64,
+            &pending[HEADER_SIZE..len - trailer],
+        )?;
+        self.record_offset = self.offset;
+        self.offset += len;
+        Ok(Some(record))
+    }
+
+    /// Пропускает байты до следующего MAGIC после текущей позиции
+    fn seek_magic(&mut self) {
+        let from = self.offset + 1;
+        self.offset = self.data[from.min(self.data.len())..]
+            .windows(MAGIC.len())
+            .position(|w| w == MAGIC)
+            .map_or(self.data.len(), |pos| from + pos);
+    }
+}
+
+impl<'a> Iterator for BinSliceReader<'a> {
+    type Item = Result<TxRecordRef<'a>, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.read_ref() {
+            Ok(record) => record.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Владеющие записи для кода, работающего с любым [RecordReader]
+impl RecordReader for BinSliceReader<'_> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        Ok(self.read_ref()?.map(TxRecord::from))
+    }
+
+    /// Ошибки пропущенных записей, [BinSliceReader::skipped] после этого пуст
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        self.skipped
+            .drain(..)
+            .map(|skipped| skipped.error)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::{
+        RecordWriter,
+        binary::{BinReader, BinWriter, CRC_SIZE},
+    };
+    use crate::record::testing::{described, encode_bin};
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    #[test]
+    fn test_slice_matches_bin_reader() {
+        let expected = BinReader::new(EXAMPLE).read_all().unwrap();
+        let records: Vec<TxRecordRef> = BinSliceReader::new(EXAMPLE)
+            .collect::<Result<_, _>>()
+            .unwrap();
+
+        assert_eq!(records.len(), 1000);
+        for (record, expected) in records.iter().zip(&expected) {
+            assert_eq!(*record, expected.to_ref());
+        }
+        // Описание указывает прямо в исходные байты
+        let range = EXAMPLE.as_ptr_range();
+        assert!(range.contains(&records[0].description.as_ptr()));
+
+        assert_eq!(BinSliceReader::new(EXAMPLE).read_all().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_mmap_file() {
+        let path = std::env::temp_dir().join(format!("ypbank-mmap-{}.bin", std::process::id()));
+        let records = vec![described(1, "first"), described(2, "второй")];
+        std::fs::write(&path, encode_bin(&records)).unwrap();
+
+        let file = BinMmap::open(&path).unwrap();
+        let decoded: Vec<TxRecord> = file.records().map(|r| r.unwrap().into()).collect();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(decoded, records);
+    }
+
+    #[test]
+    fn test_slice_strict_stops_on_error() {
+        let data = encode_bin(&[described(1, "a"), described(2, "b")]);
+        let second = data.len() / 2;
+        let mut broken = data.clone();
+        broken[second] = b'X';
+
+        let mut reader = BinSliceReader::new(&broken);
+        assert_eq!(reader.next().unwrap().unwrap().tx_id, 1);
+        let err = reader.next().unwrap().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidMagic);
+        assert_eq!(err.offset(), Some(second as u64));
+        assert!(reader.next().is_none());
+
+        let err = BinSliceReader::new(&data[..data.len() - 1])
+            .read_all()
+            .unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_slice_lenient_matches_resync() {
+        let records = vec![
+            described(1, "first"),
+            described(2, "second"),
+            described(3, "third"),
+        ];
+        let mut data = b"garbageYP".to_vec();
+        data.extend(encode_bin(&records));
+        // Портим RECORD_SIZE второй записи и обрезаем хвост
+        let second = 9 + encode_bin(&records[..1]).len();
+        data[second + 7] = 0xFF;
+        data.extend_from_slice(b"YPBN\x00\x00");
+
+        let mut reader = BinSliceReader::with_mode(&data, ParseMode::Lenient);
+        let decoded: Vec<u64> = reader.by_ref().map(|r| r.unwrap().tx_id).collect();
+        assert_eq!(decoded, vec![1, 3]);
+
+        let mut resync = BinReader::with_resync(data.as_slice());
+        resync.read_all().unwrap();
+        let offsets =
+            |skipped: &[SkippedRecord]| skipped.iter().map(|s| s.offset).collect::<Vec<_>>();
+        assert_eq!(offsets(reader.skipped()), offsets(resync.skipped()));
+        assert_eq!(reader.take_errors().len(), 3);
+    }
+
+    #[test]
+    fn test_slice_v2() {
+        let records = vec![
+            described(1, "first"),
+            described(2, "second"),
+            described(3, "third"),
+        ];
+        let mut writer = BinWriter::with_version(Vec::new(), BinVersion::V2).created_at(42);
+        writer.write_all(&records).unwrap();
+        let data = writer.into_inner().unwrap();
+
+        let mut reader = BinSliceReader::new(&data);
+        assert_eq!(reader.version(), BinVersion::V2);
+        assert_eq!(reader.read_all().unwrap(), records);
+        assert_eq!(reader.created_at(), Some(42));
+
+        // Переход по смещению из индекса без чтения заголовка файла
+        let third = data.len() - encode_bin(&records[2..]).len() - CRC_SIZE;
+        let mut reader = BinSliceReader::new(&data);
+        reader.seek(third as u64);
+        assert_eq!(reader.read_ref().unwrap().unwrap().tx_id, 3);
+
+        let mut broken = data.clone();
+        broken[third - 1] ^= 1;
+        let mut reader = BinSliceReader::with_mode(&broken, ParseMode::Lenient);
+        let decoded: Vec<u64> = reader.by_ref().map(|r| r.unwrap().tx_id).collect();
+        assert_eq!(decoded, vec![1, 3]);
+        let skipped = &reader.skipped()[0];
+        assert_matches!(skipped.error.kind, ParseErrorKind::ChecksumMismatch { .. });
+        assert_eq!(skipped.error.offset(), Some(third as u64 - CRC_SIZE as u64));
+    }
+}
@@ -0,0 +1,44 @@
+pub mod binary;
+pub mod csv;
+pub mod json;
+pub mod mmap;
+pub mod parallel;
+pub mod text;
+
+mod detect;
+mod format;
+mod traits;
+
+pub use detect::{DetectError, Detected, detect_format};
+pub use format::Format;
+pub use traits::{RecordReader, RecordWriter, Records};
+
+use crate::{
+    errors::{ParseError, ParseErrorKind},
+    record::TxRecord,
+};
+
+/// Переливает все записи из читателя в писатель по одной, возвращает их количество
+pub fn copy_records<R, W>(reader: &mut R, writer: &mut W) -> Result<u64, ParseError>
+where
+    R: RecordReader + ?Sized,
+    W: RecordWriter + ?Sized,
+{
+    let mut count = 0;
+    while let Some(record) = reader.read_record()? {
+        writer.write_record(&record)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Построчные форматы не хранят перевод строки в DESCRIPTION: такую запись не прочитать обратно
+pub(crate) fn check_line_description(record: &TxRecord) -> Result<(), ParseError> {
+    if record.description.contains(['\n', '\r']) {
+        return Err(
+            ParseError::new(ParseErrorKind::InvalidValue(record.description.clone()))
+                .with_field("DESCRIPTION"),
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,214 @@
+//! Параллельный разбор YPBankText
+//!
+//! Пустая строка полностью завершает запись, поэтому файл делится на части по пустым
+//! строкам и части разбираются [TextReader] на всех ядрах. Записи и ошибки
+//! собираются в порядке файла, номера строк в ошибках - как у последовательного чтения.
+
+use super::{RecordReader, text::TextReader};
+use crate::{
+    errors::{ParseError, ParseMode, ParseReport},
+    record::TxRecord,
+};
+use rayon::prelude::*;
+
+/// Размер части по умолчанию
+pub const DEFAULT_CHUNK_SIZE: usize = 1 << 20;
+
+/// Часть входа, разбираемая одним потоком
+struct Chunk<'a> {
+    /// Номер первой строки части в файле, с 1
+    first_line: usize,
+    data: &'a [u8],
+}
+
+/// Параллельное чтение YPBankText из памяти
+///
+/// Файл разбирается целиком при первом чтении, поэтому все записи хранятся в памяти.
+/// В строгом режиме возвращается первая по порядку файла ошибка.
+pub struct ParTextReader<'a> {
+    data: &'a [u8],
+    mode: ParseMode,
+    chunk_size: usize,
+    parsed: Option<std::vec::IntoIter<TxRecord>>,
+    errors: Vec<ParseError>,
+}
+
+impl<'a> ParTextReader<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_mode(data, ParseMode::Strict)
+    }
+
+    pub fn with_mode(data: &'a [u8], mode: ParseMode) -> Self {
+        Self {
+            data,
+            mode,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            parsed: None,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Примерный размер части в байтах: часть продолжается до ближайшей пустой строки
+    pub fn chunk_size(mut self, bytes: usize) -> Self {
+        self.chunk_size = bytes.max(1);
+        self
+    }
+
+    /// Разбирает весь вход. В нестрогом режиме ошибки пропущенных записей - в отчёте
+    pub fn parse(&self) -> Result<ParseReport<Vec<TxRecord>>, ParseError> {
+        let chunks = split(self.data, self.chunk_size);
+        let reports: Vec<_> = chunks
+            .par_iter()
+            .map(|chunk| {
+                TextReader::with_mode(chunk.data, self.mode)
+                    .starting_at(chunk.first_line)
+                    .read_report()
+            })
+            .collect();
+
+        let total = reports.iter().flatten().map(|r| r.data.len()).sum();
+        let mut data = Vec::with_capacity(total);
+        let mut errors = Vec::new();
+        for report in reports {
+            let report = report?;
+            data.extend(report.data);
+            errors.extend(report.errors);
+        }
+        Ok(ParseReport { data, errors })
+    }
+}
+
+impl RecordReader for ParTextReader<'_> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        if self.parsed.is_none() {
+            // После ошибки читатель пуст, как и последовательный после конца входа
+            self.parsed = Some(Vec::new().into_iter());
+            let report = self.parse()?;
+            self.parsed = Some(report.data.into_iter());
+            self.errors = report.errors;
+        }
+        Ok(self.parsed.as_mut().and_then(Iterator::next))
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+/// Делит вход на части примерно по `size` байт, каждая часть кончается после пустой строки
+fn split(data: &[u8], size: usize) -> Vec<Chunk<'_>> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    while start < data.len() {
+        let end = boundary(data, start.saturating_add(size).min(data.len()));
+        parts.push(&data[start..end]);
+        start = end;
+    }
+
+    let lines: Vec<usize> = parts.par_iter().map(|part| count_lines(part)).collect();
+    let mut first_line = 1;
+    parts
+        .into_iter()
+        .zip(lines)
+        .map(|(data, lines)| {
+            let chunk = Chunk { first_line, data };
+            first_line += lines;
+            chunk
+        })
+        .collect()
+}
+
+/// Конец первой пустой строки, начинающейся после `from`, или конец входа
+fn boundary(data: &[u8], from: usize) -> usize {
+    let line_end = |pos: usize| {
+        data[pos..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(data.len(), |p| pos + p + 1)
+    };
+
+    let mut pos = line_end(from);
+    while pos < data.len() {
+        let end = line_end(pos);
+        // Пустая строка с не-ASCII пробелами тоже завершает запись, но на ней
+        // достаточно не делить: часть останется длиннее
+        if data[pos..end].trim_ascii().is_empty() {
+            return end;
+        }
+        pos = end;
+    }
+    data.len()
+}
+
+fn count_lines(data: &[u8]) -> usize {
+    data.iter().filter(|&&b| b == b'\n').count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::ParseErrorKind,
+        formats::{RecordWriter, text::TextWriter},
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &str = include_str!("../../../data/records_example.txt");
+
+    #[test]
+    fn test_parallel_matches_sequential() {
+        let expected = TextReader::new(EXAMPLE.as_bytes()).read_all().unwrap();
+        for size in [1, 100, 4096, DEFAULT_CHUNK_SIZE] {
+            let records = ParTextReader::new(EXAMPLE.as_bytes())
+                .chunk_size(size)
+                .read_all()
+                .unwrap();
+            assert_eq!(records, expected, "chunk_size {}", size);
+        }
+
+        let mut writer = TextWriter::new(Vec::new());
+        writer.write_all(&expected).unwrap();
+        let written = writer.into_inner().unwrap();
+        let chunks = split(&written, 1000);
+        assert!(chunks.len() > 100);
+        assert!(chunks.iter().all(|c| c.data.ends_with(b"\n\n")));
+        assert_eq!(chunks[1].first_line, count_lines(chunks[0].data) + 1);
+    }
+
+    #[test]
+    fn test_parallel_error_positions() {
+        let mut broken = EXAMPLE.replacen("AMOUNT: 50000", "AMOUNT: x", 1);
+        broken = broken.replacen("STATUS: FAILURE", "STATUS: ?", 1);
+        let sequential = TextReader::new(broken.as_bytes()).read_all().unwrap_err();
+
+        let err = ParTextReader::new(broken.as_bytes())
+            .chunk_size(64)
+            .read_all()
+            .unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::InvalidValue(_));
+        assert_eq!(err.position, sequential.position);
+        assert_eq!(err.field, sequential.field);
+
+        let mut lenient = TextReader::with_mode(broken.as_bytes(), ParseMode::Lenient);
+        let expected = lenient.read_report().unwrap();
+        let report = ParTextReader::with_mode(broken.as_bytes(), ParseMode::Lenient)
+            .chunk_size(64)
+            .parse()
+            .unwrap();
+        assert_eq!(report.data, expected.data);
+        let lines = |errors: &[ParseError]| errors.iter().map(|e| e.line()).collect::<Vec<_>>();
+        assert_eq!(lines(&report.errors), lines(&expected.errors));
+        assert_eq!(report.errors.len(), 2);
+    }
+
+    #[test]
+    fn test_split_edge_cases() {
+        assert!(split(b"", 10).is_empty());
+        let data = b"A: 1\n \t\nB: 2\n\n\nC: 3";
+        let chunks = split(data, 1);
+        let parts: Vec<&[u8]> = chunks.iter().map(|c| c.data).collect();
+        assert_eq!(parts, vec![&b"A: 1\n \t\n"[..], b"B: 2\n\n", b"\nC: 3"]);
+        let lines: Vec<usize> = chunks.iter().map(|c| c.first_line).collect();
+        assert_eq!(lines, vec![1, 3, 5]);
+    }
+}
@@ -0,0 +1,386 @@
+use super::{RecordReader, RecordWriter, check_line_description};
+use crate::{
+    errors::{Line, ParseError, ParseErrorKind, ParseMode},
+    record::{FIELDS, TxRecord, TxStatus, TxType},
+};
+use std::{
+    io::{BufRead, Write},
+    ops::Range,
+};
+
+/// Чтение записей формата YPBankText
+///
+/// В нестрогом режиме запись с ошибкой дочитывается до пустой строки и пропускается.
+pub struct TextReader<R: BufRead> {
+    inner: R,
+    buf: Vec<u8>,
+    line: usize,
+    mode: ParseMode,
+    errors: Vec<ParseError>,
+}
+
+/// Запись, поля которой ещё собираются
+#[derive(Default)]
+struct PartialRecord {
+    tx_id: Option<u64>,
+    tx_type: Option<TxType>,
+    from_user_id: Option<u64>,
+    to_user_id: Option<u64>,
+    amount: Option<i64>,
+    timestamp: Option<u64>,
+    status: Option<TxStatus>,
+    description: Option<String>,
+    /// Строки, на которых встретились поля, в порядке [FIELDS]; `0` - поля ещё не было
+    lines: [usize; FIELDS.len()],
+}
+
+impl PartialRecord {
+    fn set(&mut self, line: &Line, key: &str, value: &str) -> Result<(), ParseError> {
+        let Some(index) = FIELDS.iter().position(|f| *f == key) else {
+            return Err(line.error(ParseErrorKind::UnknownField(key.to_string()), key));
+        };
+        let field = FIELDS[index];
+        if self.lines[index] != 0 {
+            return Err(line
+                .error(
+                    ParseErrorKind::DuplicateField {
+                        first_line: self.lines[index],
+                    },
+                    key,
+                )
+                .with_field(field));
+        }
+        self.lines[index] = line.number;
+
+        match field {
+            "TX_ID" => self.tx_id = Some(line.parse(field, value)?),
+            "TX_TYPE" => self.tx_type = Some(line.parse(field, value)?),
+            "FROM_USER_ID" => self.from_user_id = Some(line.parse(field, value)?),
+            "TO_USER_ID" => self.to_user_id = Some(line.parse(field, value)?),
+            "AMOUNT" => self.amount = Some(line.parse(field, value)?),
+            "TIMESTAMP" => self.timestamp = Some(line.parse(field, value)?),
+            "STATUS" => self.status = Some(line.parse(field, value)?),
+            _ => {
+                let description = value
+                    .strip_prefix('"')
+                    .and_then(|d| d.strip_suffix('"'))
+                    .ok_or_else(|| {
+                        line.error(ParseErrorKind::InvalidValue(value.to_string()), value)
+                            .with_field(field)
+                    })?;
+                self.description = Some(description.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self, line: usize) -> Result<TxRecord, ParseError> {
+        let missing = |field| {
+            ParseError::new(ParseErrorKind::MissingField)
+                .at_line(line, 1)
+                .with_field(field)
+        };
+        Ok(TxRecord {
+            tx_id: self.tx_id.ok_or_else(|| missing("TX_ID"))?,
+            tx_type: self.tx_type.ok_or_else(|| missing("TX_TYPE"))?,
+            from_user_id: self.from_user_id.ok_or_else(|| missing("FROM_USER_ID"))?,
+            to_user_id: self.to_user_id.ok_or_else(|| missing("TO_USER_ID"))?,
+            amount: self.amount.ok_or_else(|| missing("AMOUNT"))?,
+            timestamp: self.timestamp.ok_or_else(|| missing("TIMESTAMP"))?,
+            status: self.status.ok_or_else(|| missing("STATUS"))?,
+            description: self.description.ok_or_else(|| missing("DESCRIPTION"))?,
+        })
+    }
+}
+
+impl<R: BufRead> TextReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_mode(inner, ParseMode::Strict)
+    }
+
+    pub fn with_mode(inner: R, mode: ParseMode) -> Self {
+        Self {
+            inner,
+            buf: Vec::new(),
+            line: 0,
+            mode,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Нумерует строки с `line`, когда вход - часть файла, начинающаяся не с первой строки
+    pub fn starting_at(mut self, line: usize) -> Self {
+        self.line = line.saturating_sub(1);
+        self
+    }
+
+    fn try_read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        let mut record = PartialRecord::default();
+        // Строка, с которой началась запись
+        let mut start = None;
+        // Первая ошибка записи, в нестрогом режиме запись после неё дочитывается
+        let mut error = None;
+
+        loop {
+            self.buf.clear();
+            let eof = self.inner.read_until(b'\n', &mut self.buf)? == 0;
+            if !eof {
+                self.line += 1;
+            }
+            // Строка с неверным UTF-8 - часть записи с ошибкой, если это не комментарий
+            let line = Line::decode_with(self.line, &self.buf, utf8_field);
+            let text = line.as_ref().map_or("", |line| line.text.trim());
+            let comment = match &line {
+                Ok(_) => text.starts_with('#'),
+                Err(_) => self.buf.trim_ascii_start().starts_with(b"#"),
+            };
+
+            if eof || (line.is_ok() && text.is_empty()) {
+                match start {
+                    Some(start) => {
+                        return match error {
+                            Some(err) => Err(err),
+                            None => record.finish(start).map(Some),
+                        };
+                    }
+                    None if eof => return Ok(None),
+                    None => continue,
+                }
+            }
+            if comment {
+                continue;
+            }
+            start.get_or_insert(self.line);
+            if error.is_some() {
+                continue;
+            }
+
+            let result = line.and_then(|line| match text.split_once(':') {
+                Some((key, value)) => record.set(&line, key.trim(), value.trim()),
+                None => Err(line.error(ParseErrorKind::InvalidLine, text)),
+            });
+            if let Err(err) = result {
+                if self.mode == ParseMode::Strict {
+                    return Err(err);
+                }
+                error = Some(err);
+            }
+        }
+    }
+}
+
+/// Поле строки `KEY: value` с неверным UTF-8 на смещении `at`, если оно в значении
+fn utf8_field(text: &str, at: usize) -> Option<(&'static str, Range<usize>)> {
+    let (key, value) = text.split_once(':')?;
+    let field = FIELDS.iter().find(|f| **f == key.trim())?;
+    let start = text.len() - value.trim_start().len();
+    let end = text.trim_end().len();
+    (at >= start).then_some((*field, start..end))
+}
+
+impl<R: BufRead> RecordReader for TextReader<R> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        loop {
+            match self.try_read_record() {
+                Err(err) if self.mode == ParseMode::Lenient && !err.is_io() => {
+                    self.errors.push(err)
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        std::mem::take(&mut self.errors)
+    }
+}
+
+/// Запись в формате YPBankText. Перед каждой записью пишется комментарий с её номером
+pub struct TextWriter<W: Write> {
+    inner: W,
+    count: usize,
+}
+
+impl<W: Write> TextWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, count: 0 }
+    }
+
+    /// Сбрасывает буфер и возвращает внутренний writer
+    pub fn into_inner(mut self) -> Result<W, ParseError> {
+        self.finish()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write> RecordWriter for TextWriter<W> {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        check_line_description(record)?;
+        self.count += 1;
+        writeln!(self.inner, "# Record {} ({})", self.count, record.tx_type)?;
+        writeln!(self.inner, "TX_ID: {}", record.tx_id)?;
+        writeln!(self.inner, "TX_TYPE: {}", record.tx_type)?;
+        writeln!(self.inner, "FROM_USER_ID: {}", record.from_user_id)?;
+        writeln!(self.inner, "TO_USER_ID: {}", record.to_user_id)?;
+        writeln!(self.inner, "AMOUNT: {}", record.amount)?;
+        writeln!(self.inner, "TIMESTAMP: {}", record.timestamp)?;
+        writeln!(self.inner, "STATUS: {}", record.status)?;
+        writeln!(self.inner, "DESCRIPTION: \"{}\"", record.description)?;
+        writeln!(self.inner)?;
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        self.inner.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        errors::{Position, Snippet},
+        formats::csv::CsvReader,
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &str = include_str!("../../../data/records_example.txt");
+    const EXAMPLE_CSV: &str = include_str!("../../../data/records_example.csv");
+
+    const SPEC_EXAMPLE: &str = r#"# Record 1 (Deposit)
+TX_ID: 1234567890123456
+TX_TYPE: DEPOSIT
+FROM_USER_ID: 0
+TO_USER_ID: 9876543210987654
+AMOUNT: 10000
+TIMESTAMP: 1633036800000
+STATUS: SUCCESS
+DESCRIPTION: "Terminal deposit"
+
+# Record 2 (Transfer)
+TX_ID: 2312321321321321
+TIMESTAMP: 1633056800000
+STATUS: FAILURE
+TX_TYPE: TRANSFER
+FROM_USER_ID: 1231231231231231
+TO_USER_ID: 9876543210987654
+AMOUNT: 1000
+DESCRIPTION: "User transfer"
+"#;
+
+    #[test]
+    fn test_text_read_spec_example() {
+        let records = TextReader::new(SPEC_EXAMPLE.as_bytes()).read_all().unwrap();
+
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].tx_type, TxType::Deposit);
+        assert_eq!(records[0].description, "Terminal deposit");
+        assert_eq!(records[1].tx_id, 2312321321321321);
+        assert_eq!(records[1].status, TxStatus::Failure);
+        assert_eq!(records[1].amount, 1000);
+    }
+
+    #[test]
+    fn test_text_read_missing_field() {
+        let data = "# comment\nTX_ID: 1\nTX_TYPE: DEPOSIT\n\n";
+        let err = TextReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::MissingField);
+        assert_eq!(err.field, Some("FROM_USER_ID"));
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn test_text_read_duplicate_field() {
+        let data = format!("{}\n\nTX_ID: 1\nTX_TYPE: DEPOSIT\nTX_ID: 2\n", SPEC_EXAMPLE);
+        let err = TextReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::DuplicateField { first_line: 22 });
+        assert_eq!(err.field, Some("TX_ID"));
+        assert_eq!(err.line(), Some(24));
+    }
+
+    #[test]
+    fn test_text_read_unknown_field() {
+        let data = "TX_ID: 1\nCURRENCY: USD\n";
+        let err = TextReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_matches!(err.kind, ParseErrorKind::UnknownField(ref f) if f == "CURRENCY");
+        assert_eq!(err.line(), Some(2));
+    }
+
+    #[test]
+    fn test_text_read_invalid_value_position() {
+        let data = "TX_ID: 1\n  AMOUNT:  -x\n";
+        let err = TextReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_eq!(err.field, Some("AMOUNT"));
+        assert_eq!(
+            err.position,
+            Some(Position::Line {
+                line: 2,
+                column: 12
+            })
+        );
+        assert_eq!(err.snippet, Some(Snippet::new("  AMOUNT:  -x", 11, 2)));
+    }
+
+    #[test]
+    fn test_text_read_lenient() {
+        let data = format!(
+            "TX_ID: 1\nAMOUNT: x\nTX_TYPE: DEPOSIT\n\n# comment\nTX_ID: 2\n\n{}",
+            SPEC_EXAMPLE
+        );
+        let err = TextReader::new(data.as_bytes()).read_all().unwrap_err();
+        assert_eq!(err.field, Some("AMOUNT"));
+
+        let report = TextReader::with_mode(data.as_bytes(), ParseMode::Lenient)
+            .read_report()
+            .unwrap();
+        assert_eq!(report.data.len(), 2);
+        assert_eq!(report.data[0].tx_id, 1234567890123456);
+        assert_eq!(report.errors.len(), 2);
+        assert_eq!(report.errors[0].field, Some("AMOUNT"));
+        assert_eq!(report.errors[0].line(), Some(2));
+        assert_matches!(report.errors[1].kind, ParseErrorKind::MissingField);
+        assert_eq!(report.errors[1].line(), Some(6));
+
+        // Неверный UTF-8 - ошибка записи, чтение продолжается со следующей
+        let data = [
+            b"TX_ID: 1\nDESCRIPTION: \"\xFF\"\n# \xFF\n\n",
+            SPEC_EXAMPLE.as_bytes(),
+        ]
+        .concat();
+        let report = TextReader::with_mode(data.as_slice(), ParseMode::Lenient)
+            .read_report()
+            .unwrap();
+        assert_eq!(report.data.len(), 2);
+        let positions: Vec<_> = report.errors.iter().map(|e| e.position).collect();
+        assert_eq!(
+            positions,
+            vec![Some(Position::Line {
+                line: 2,
+                column: 15
+            })]
+        );
+        assert_eq!(report.errors[0].field, Some("DESCRIPTION"));
+    }
+
+    #[test]
+    fn test_text_example_matches_csv() {
+        let records = TextReader::new(EXAMPLE.as_bytes()).read_all().unwrap();
+        let csv_records = CsvReader::new(EXAMPLE_CSV.as_bytes()).read_all().unwrap();
+        assert_eq!(records.len(), 1000);
+        assert_eq!(records, csv_records);
+    }
+
+    #[test]
+    fn test_text_write_read() {
+        let records = TextReader::new(EXAMPLE.as_bytes()).read_all().unwrap();
+        let mut writer = TextWriter::new(Vec::new());
+        writer.write_all(&records).unwrap();
+        let output = writer.into_inner().unwrap();
+
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.starts_with("# Record 1 (DEPOSIT)\nTX_ID: 1000000000000000\n"));
+        let parsed = TextReader::new(output.as_bytes()).read_all().unwrap();
+        assert_eq!(parsed, records);
+    }
+}
@@ -0,0 +1,138 @@
+use crate::{
+    errors::{ParseError, ParseReport},
+    record::TxRecord,
+};
+
+/// Источник записей YPBank, не зависящий от формата
+pub trait RecordReader {
+    /// Читает следующую запись, `None` - конец файла
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError>;
+
+    /// Читает все оставшиеся записи
+    fn read_all(&mut self) -> Result<Vec<TxRecord>, ParseError> {
+        let mut records = Vec::new();
+        while let Some(record) = self.read_record()? {
+            records.push(record);
+        }
+        Ok(records)
+    }
+
+    /// Забирает ошибки записей, пропущенных в нестрогом режиме с прошлого вызова
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        Vec::new()
+    }
+
+    /// Читает все оставшиеся записи вместе с ошибками пропущенных
+    fn read_report(&mut self) -> Result<ParseReport<Vec<TxRecord>>, ParseError> {
+        let data = self.read_all()?;
+        Ok(ParseReport {
+            data,
+            errors: self.take_errors(),
+        })
+    }
+
+    /// Итератор по записям. Записи читаются по одной, файл целиком в память не загружается
+    fn records(self) -> Records<Self>
+    where
+        Self: Sized,
+    {
+        Records {
+            reader: self,
+            failed: false,
+        }
+    }
+}
+
+/// Потоковый итератор по записям читателя, см. [RecordReader::records]
+///
+/// После первой ошибки итерация заканчивается: позиция в потоке уже не определена.
+pub struct Records<R: RecordReader> {
+    reader: R,
+    failed: bool,
+}
+
+impl<R: RecordReader> Records<R> {
+    /// Возвращает читателя, например чтобы забрать пропущенные записи
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: RecordReader> Iterator for Records<R> {
+    type Item = Result<TxRecord, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.reader.read_record() {
+            Ok(record) => record.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Приёмник записей YPBank, не зависящий от формата
+pub trait RecordWriter {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError>;
+
+    /// Дописывает служебные данные формата и сбрасывает буфер
+    fn finish(&mut self) -> Result<(), ParseError>;
+
+    fn write_all(&mut self, records: &[TxRecord]) -> Result<(), ParseError> {
+        for record in records {
+            self.write_record(record)?;
+        }
+        Ok(())
+    }
+}
+
+impl<T: RecordReader + ?Sized> RecordReader for Box<T> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        (**self).read_record()
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        (**self).take_errors()
+    }
+}
+
+impl<T: RecordReader + ?Sized> RecordReader for &mut T {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        (**self).read_record()
+    }
+
+    fn take_errors(&mut self) -> Vec<ParseError> {
+        (**self).take_errors()
+    }
+}
+
+/// Записи, уже загруженные в память
+impl RecordReader for std::vec::IntoIter<TxRecord> {
+    fn read_record(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        Ok(self.next())
+    }
+}
+
+impl<T: RecordWriter + ?Sized> RecordWriter for Box<T> {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        (**self).write_record(record)
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        (**self).finish()
+    }
+}
+
+impl<T: RecordWriter + ?Sized> RecordWriter for &mut T {
+    fn write_record(&mut self, record: &TxRecord) -> Result<(), ParseError> {
+        (**self).write_record(record)
+    }
+
+    fn finish(&mut self) -> Result<(), ParseError> {
+        (**self).finish()
+    }
+}
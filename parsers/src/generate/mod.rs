@@ -0,0 +1,628 @@
+//! Генератор синтетических записей YPBank
+//!
+//! Записи определяются зерном и настройками: при том же зерне получается тот же поток
+//! в любой версии, поэтому нагрузочные тесты и фикстуры можно генерировать, а не хранить.
+//! Генератор - итератор, память не зависит от количества записей,
+//! кроме режима [Generator::consistent], где хранятся балансы пользователей.
+//!
+//! Записи упорядочены по TIMESTAMP, TX_ID идут подряд. Пользователи - `1..=users`,
+//! внешний счёт 0 - источник DEPOSIT и получатель WITHDRAWAL. Результат проходит
+//! [Validator::standard](crate::validate::Validator::standard) для формата, под который
+//! задан знак сумм, см. [Generator::signed_amounts].
+
+use crate::{
+    errors::ParseError,
+    formats::RecordWriter,
+    query::terms::{self, Range},
+    record::{TxRecord, TxStatus, TxType},
+};
+use std::{collections::HashMap, fmt::Display, ops::Bound, str::FromStr};
+
+/// TX_ID первой записи по умолчанию, как в `data/records_example.*`
+pub const DEFAULT_FIRST_TX_ID: u64 = 1_000_000_000_000_000;
+
+/// Начало времени по умолчанию, как в `data/records_example.*`
+pub const DEFAULT_START: u64 = 1_633_036_860_000;
+
+/// Длина периода по умолчанию - 30 дней
+pub const DEFAULT_PERIOD: u64 = 30 * 24 * 60 * 60 * 1000;
+
+/// Генератор псевдослучайных чисел SplitMix64: быстрый и одинаковый на всех платформах
+#[derive(Debug, Clone)]
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Равномерно из `0..n`, `n > 0`
+    fn below(&mut self, n: u64) -> u64 {
+        // Отбрасывание хвоста убирает смещение остатка от деления
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let value = self.next_u64();
+            if value < zone {
+                return value % n;
+            }
+        }
+    }
+
+    /// Равномерно из `[0, 1)`
+    fn unit(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+/// Веса значений: значение выбирается с вероятностью, пропорциональной весу
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Weights<T> {
+    items: Vec<(T, u32)>,
+    total: u64,
+}
+
+impl<T: Copy> Weights<T> {
+    /// Хотя бы один вес должен быть ненулевым
+    pub fn new(items: impl IntoIterator<Item = (T, u32)>) -> Result<Self, String> {
+        let items: Vec<(T, u32)> = items.into_iter().collect();
+        let total = items.iter().map(|&(_, weight)| u64::from(weight)).sum();
+        if total == 0 {
+            return Err("все веса нулевые".to_string());
+        }
+        Ok(Self { items, total })
+    }
+
+    /// Вероятность значения `value`
+    pub fn share(&self, value: T) -> f64
+    where
+        T: PartialEq,
+    {
+        let weight: u64 = self
+            .items
+            .iter()
+            .filter(|(item, _)| *item == value)
+            .map(|&(_, weight)| u64::from(weight))
+            .sum();
+        weight as f64 / self.total as f64
+    }
+
+    fn pick(&self, rng: &mut SplitMix64) -> T {
+        let mut point = rng.below(self.total);
+        for &(item, weight) in &self.items {
+            if point < u64::from(weight) {
+                return item;
+            }
+            point -= u64::from(weight);
+        }
+        unreachable!("точка меньше суммы весов")
+    }
+}
+
+/// Список `значение=вес` через запятую, регистр не важен: `deposit=1,transfer=3`.
+/// Не названные значения получают вес 0
+impl<T: Copy + FromStr> FromStr for Weights<T> {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let items = value
+            .split(',')
+            .map(|item| {
+                let (name, weight) = item
+                    .split_once('=')
+                    .ok_or_else(|| format!("{:?} - не пара значение=вес", item.trim()))?;
+                let name = terms::list::<T>(name)?.remove(0);
+                Ok((name, terms::number(weight)?))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Self::new(items)
+    }
+}
+
+/// Распределение AMOUNT, в наименьших единицах валюты
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Amounts {
+    /// Равномерно от `min` до `max` включительно
+    Uniform { min: u64, max: u64 },
+    /// Равномерно по логарифму: мелких сумм много, крупных мало, как в реальных платежах
+    LogUniform { min: u64, max: u64 },
+}
+
+impl Amounts {
+    /// `min` от 1, `max` не меньше `min` и не больше `i64::MAX`
+    fn check(min: u64, max: u64) -> Result<(), String> {
+        if min == 0 || max < min || max > i64::MAX as u64 {
+            return Err(format!("неверный диапазон сумм {}..={}", min, max));
+        }
+        Ok(())
+    }
+
+    /// Границы, сведённые к `1..=i64::MAX`, `max` не меньше `min`
+    fn clamped(self) -> Self {
+        let bounds = |min: u64, max: u64| {
+            let min = min.clamp(1, i64::MAX as u64);
+            (min, max.clamp(min, i64::MAX as u64))
+        };
+        match self {
+            Amounts::Uniform { min, max } => {
+                let (min, max) = bounds(min, max);
+                Amounts::Uniform { min, max }
+            }
+            Amounts::LogUniform { min, max } => {
+                let (min, max) = bounds(min, max);
+                Amounts::LogUniform { min, max }
+            }
+        }
+    }
+
+    fn sample(&self, rng: &mut SplitMix64) -> u64 {
+        match *self {
+            Amounts::Uniform { min, max } => match (max - min).checked_add(1) {
+                Some(span) => min + rng.below(span),
+                None => rng.next_u64(),
+            },
+            Amounts::LogUniform { min, max } => {
+                let (low, high) = ((min as f64).ln(), (max as f64).ln());
+                let amount = (low + rng.unit() * (high - low)).exp().round() as u64;
+                amount.clamp(min, max)
+            }
+        }
+    }
+}
+
+/// По логарифму от 100 до 1 000 000 центов
+impl Default for Amounts {
+    fn default() -> Self {
+        Amounts::LogUniform {
+            min: 100,
+            max: 1_000_000,
+        }
+    }
+}
+
+impl Display for Amounts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Amounts::Uniform { min, max } => write!(f, "{}..={}", min, max),
+            Amounts::LogUniform { min, max } => write!(f, "log:{}..={}", min, max),
+        }
+    }
+}
+
+/// `MIN..=MAX` или `MIN..MAX` - равномерно, `log:MIN..=MAX` - по логарифму
+impl FromStr for Amounts {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (log, range) = match value.strip_prefix("log:") {
+            Some(range) => (true, range),
+            None => (false, value),
+        };
+        let (min, max) = bounded(
+            terms::range(range, terms::number::<u64>, |_| {
+                Err(format!("{:?} - не диапазон", value))
+            })?,
+            value,
+        )?;
+        let max = max
+            .checked_sub(1)
+            .ok_or_else(|| format!("пустой диапазон {:?}", value))?;
+        Amounts::check(min, max)?;
+        Ok(match log {
+            true => Amounts::LogUniform { min, max },
+            false => Amounts::Uniform { min, max },
+        })
+    }
+}
+
+/// Диапазон с обеими границами как `start..end`
+fn bounded(range: Range<u64>, value: &str) -> Result<(u64, u64), String> {
+    let start = match range.0 {
+        Bound::Included(start) => start,
+        _ => return Err(format!("{:?} - нет начала диапазона", value)),
+    };
+    let end = match range.1 {
+        Bound::Excluded(end) => end,
+        Bound::Included(end) => end.saturating_add(1),
+        Bound::Unbounded => return Err(format!("{:?} - нет конца диапазона", value)),
+    };
+    Ok((start, end))
+}
+
+/// Период времени `ОТ..ДО` в миллисекундах: числа или даты UTC, как в условии `time` запросов.
+/// Одна дата - весь её период: `2021-03` - март
+pub fn time_range(value: &str) -> Result<(u64, u64), String> {
+    let (start, end) = bounded(terms::time_range(value)?, value)?;
+    if end <= start {
+        return Err(format!("пустой период {:?}", value));
+    }
+    Ok((start, end))
+}
+
+/// Настройки генератора
+///
+/// ```
+/// use parsers::{TxStatus, generate::{Amounts, Generator, Weights}};
+///
+/// let generator = Generator::new(42)
+///     .users(10)
+///     .statuses(Weights::new([(TxStatus::Success, 9), (TxStatus::Failure, 1)]).unwrap())
+///     .amounts(Amounts::Uniform { min: 100, max: 500 })
+///     .consistent(true);
+/// let records: Vec<_> = generator.records(100).collect();
+/// assert_eq!(records.len(), 100);
+/// assert_eq!(records, generator.records(100).collect::<Vec<_>>());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Generator {
+    seed: u64,
+    users: u64,
+    types: Weights<TxType>,
+    statuses: Weights<TxStatus>,
+    amounts: Amounts,
+    start: u64,
+    end: u64,
+    first_tx_id: u64,
+    consistent: bool,
+    signed_amounts: bool,
+}
+
+impl Generator {
+    /// Генератор с зерном `seed`: 100 пользователей, типы поровну,
+    /// статусы SUCCESS:FAILURE:PENDING как 8:1:1, 30 дней от 2021-09-30
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            users: 100,
+            types: Weights::new([
+                (TxType::Deposit, 1),
+                (TxType::Transfer, 1),
+                (TxType::Withdrawal, 1),
+            ])
+            .expect("веса ненулевые"),
+            statuses: Weights::new([
+                (TxStatus::Success, 8),
+                (TxStatus::Failure, 1),
+                (TxStatus::Pending, 1),
+            ])
+            .expect("веса ненулевые"),
+            amounts: Amounts::default(),
+            start: DEFAULT_START,
+            end: DEFAULT_START + DEFAULT_PERIOD,
+            first_tx_id: DEFAULT_FIRST_TX_ID,
+            consistent: false,
+            signed_amounts: false,
+        }
+    }
+
+    /// Количество пользователей, не меньше 1
+    pub fn users(mut self, users: u64) -> Self {
+        self.users = users.max(1);
+        self
+    }
+
+    pub fn types(mut self, types: Weights<TxType>) -> Self {
+        self.types = types;
+        self
+    }
+
+    pub fn statuses(mut self, statuses: Weights<TxStatus>) -> Self {
+        self.statuses = statuses;
+        self
+    }
+
+    /// Распределение сумм. Границы обрезаются до `1..=i64::MAX`, `max` меньше `min` заменяется на `min`
+    pub fn amounts(mut self, amounts: Amounts) -> Self {
+        self.amounts = amounts.clamped();
+        self
+    }
+
+    /// Период TIMESTAMP `start..end` в миллисекундах. Пустой период расширяется до одной миллисекунды
+    pub fn time_range(mut self, start: u64, end: u64) -> Self {
+        self.start = start.min(u64::MAX - 1);
+        self.end = end.max(self.start.saturating_add(1));
+        self
+    }
+
+    pub fn first_tx_id(mut self, tx_id: u64) -> Self {
+        self.first_tx_id = tx_id;
+        self
+    }
+
+    /// Только последовательности, согласованные с балансами: успешные списания и переводы
+    /// не превышают баланс отправителя. Сумма урезается до баланса, а при нулевом балансе
+    /// вместо списания генерируется пополнение этого пользователя
+    pub fn consistent(mut self, consistent: bool) -> Self {
+        self.consistent = consistent;
+        self
+    }
+
+    /// Суммы WITHDRAWAL отрицательные, как требует `bin`. В `csv`, `text` и `json`
+    /// суммы неотрицательные, по умолчанию так
+    pub fn signed_amounts(mut self, signed: bool) -> Self {
+        self.signed_amounts = signed;
+        self
+    }
+
+    /// Первые `count` записей
+    pub fn records(&self, count: u64) -> Records {
+        Records {
+            config: self.clone(),
+            rng: SplitMix64(self.seed),
+            index: 0,
+            count,
+            balances: HashMap::new(),
+        }
+    }
+
+    /// Пишет `count` записей в `writer`, [RecordWriter::finish] вызывает владелец
+    pub fn write<W: RecordWriter + ?Sized>(
+        &self,
+        count: u64,
+        writer: &mut W,
+    ) -> Result<u64, ParseError> {
+        for record in self.records(count) {
+            writer.write_record(&record)?;
+        }
+        Ok(count)
+    }
+}
+
+/// Итератор по записям генератора
+pub struct Records {
+    config: Generator,
+    rng: SplitMix64,
+    index: u64,
+    count: u64,
+    /// Балансы пользователей с ненулевым балансом, только в согласованном режиме
+    balances: HashMap<u64, u64>,
+}
+
+impl Records {
+    fn user(&mut self) -> u64 {
+        1 + self.rng.below(self.config.users)
+    }
+
+    /// Получатель перевода, по возможности не совпадающий с отправителем
+    fn other_user(&mut self, from: u64) -> u64 {
+        if self.config.users == 1 {
+            return from;
+        }
+        let to = 1 + self.rng.below(self.config.users - 1);
+        if to >= from { to + 1 } else { to }
+    }
+
+    /// Момент записи номер `index`: период делится на равные доли, внутри доли - случайно
+    fn timestamp(&mut self) -> u64 {
+        let span = u128::from(self.config.end - self.config.start);
+        let (index, count) = (u128::from(self.index), u128::from(self.count));
+        let slot = (span * index / count) as u64;
+        let next = (span * (index + 1) / count) as u64;
+        self.config.start + slot + self.rng.below((next - slot).max(1))
+    }
+
+    fn generate(&mut self) -> TxRecord {
+        let timestamp = self.timestamp();
+        let mut tx_type = self.config.types.pick(&mut self.rng);
+        let status = self.config.statuses.pick(&mut self.rng);
+        let mut amount = self
+            .config
+            .amounts
+            .sample(&mut self.rng)
+            .clamp(1, i64::MAX as u64);
+        let (mut from, mut to) = match tx_type {
+            TxType::Deposit => (0, self.user()),
+            TxType::Transfer => {
+                let from = self.user();
+                (from, self.other_user(from))
+            }
+            TxType::Withdrawal => (self.user(), 0),
+        };
+
+        if self.config.consistent && status == TxStatus::Success {
+            if tx_type != TxType::Deposit {
+                let balance = self.balances.get(&from).copied().unwrap_or(0);
+                if balance == 0 {
+                    (tx_type, to, from) = (TxType::Deposit, from, 0);
+                } else {
+                    amount = amount.min(balance);
+                }
+            }
+            if from != 0 {
+                let balance = self.balances.get_mut(&from).expect("баланс проверен");
+                *balance -= amount;
+                if *balance == 0 {
+                    self.balances.remove(&from);
+                }
+            }
+            if to != 0 {
+                let balance = self.balances.entry(to).or_insert(0);
+                *balance = balance.saturating_add(amount);
+            }
+        }
+
+        let amount = amount as i64;
+        let index = self.index;
+        self.index += 1;
+        TxRecord {
+            tx_id: self.config.first_tx_id.wrapping_add(index),
+            tx_type,
+            from_user_id: from,
+            to_user_id: to,
+            amount: match tx_type {
+                TxType::Withdrawal if self.config.signed_amounts => -amount,
+                _ => amount,
+            },
+            timestamp,
+            status,
+            description: format!("Record number {}", index + 1),
+        }
+    }
+}
+
+impl Iterator for Records {
+    type Item = TxRecord;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        (self.index < self.count).then(|| self.generate())
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let left = (self.count - self.index) as usize;
+        (left, Some(left))
+    }
+}
+
+impl ExactSizeIterator for Records {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Format, validate::Validator};
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn test_generated_records_are_valid() {
+        for format in Format::ALL {
+            let generator = Generator::new(7).signed_amounts(format == Format::Bin);
+            let mut validator = Validator::standard(format);
+            let mut last = 0;
+            for record in generator.records(3000) {
+                assert_eq!(validator.check(&record), vec![], "{}", format);
+                assert!(record.timestamp >= last);
+                last = record.timestamp;
+            }
+            assert!(last < DEFAULT_START + DEFAULT_PERIOD);
+        }
+    }
+
+    #[test]
+    fn test_generator_is_deterministic() {
+        let records: Vec<_> = Generator::new(1).records(500).collect();
+        assert_eq!(records, Generator::new(1).records(500).collect::<Vec<_>>());
+        assert_ne!(records, Generator::new(2).records(500).collect::<Vec<_>>());
+        // Начало потока не зависит от его длины
+        assert_eq!(
+            records[0].tx_type,
+            Generator::new(1).records(10).next().unwrap().tx_type
+        );
+        assert_eq!(records[0].tx_id, DEFAULT_FIRST_TX_ID);
+        assert_eq!(records[499].description, "Record number 500");
+    }
+
+    #[test]
+    fn test_generator_distributions() {
+        let generator = Generator::new(3)
+            .users(5)
+            .types("transfer=3,withdrawal=1".parse().unwrap())
+            .statuses("pending=1".parse().unwrap())
+            .amounts("200..=300".parse().unwrap());
+        let records: Vec<_> = generator.records(4000).collect();
+        let transfers = records
+            .iter()
+            .filter(|r| r.tx_type == TxType::Transfer)
+            .count();
+        assert!((2800..3200).contains(&transfers), "{}", transfers);
+        for record in &records {
+            assert_ne!(record.tx_type, TxType::Deposit);
+            assert_eq!(record.status, TxStatus::Pending);
+            assert!((200..=300).contains(&record.amount));
+            assert!(record.from_user_id <= 5 && record.to_user_id <= 5);
+            if record.tx_type == TxType::Transfer {
+                assert_ne!(record.from_user_id, record.to_user_id);
+            }
+        }
+
+        let amounts = Amounts::LogUniform {
+            min: 1,
+            max: 1_000_000,
+        };
+        let mut rng = SplitMix64(0);
+        let small = (0..10_000)
+            .filter(|_| amounts.sample(&mut rng) < 1000)
+            .count();
+        // Половина логарифмического диапазона - суммы до 1000
+        assert!((4500..5500).contains(&small), "{}", small);
+    }
+
+    #[test]
+    fn test_consistent_balances() {
+        let generator = Generator::new(11)
+            .users(20)
+            .types("transfer=4,withdrawal=4,deposit=1".parse().unwrap())
+            .consistent(true);
+        let mut balances: HashMap<u64, i128> = HashMap::new();
+        let mut debits = 0;
+        for record in generator.records(5000) {
+            if record.status != TxStatus::Success {
+                continue;
+            }
+            let amount = i128::from(record.amount);
+            *balances.entry(record.to_user_id).or_default() += amount;
+            if record.from_user_id != 0 {
+                debits += 1;
+                let balance = balances.entry(record.from_user_id).or_default();
+                *balance -= amount;
+                assert!(*balance >= 0, "TX_ID {}", record.tx_id);
+            }
+        }
+        assert!(debits > 1000);
+    }
+
+    #[test]
+    fn test_parse_settings() {
+        assert_eq!(
+            "log:100..=5000".parse(),
+            Ok(Amounts::LogUniform {
+                min: 100,
+                max: 5000
+            })
+        );
+        assert_eq!("1..10".parse(), Ok(Amounts::Uniform { min: 1, max: 9 }));
+        assert!("0..=10".parse::<Amounts>().is_err());
+        assert!("100..".parse::<Amounts>().is_err());
+        assert!("100".parse::<Amounts>().is_err());
+
+        let types: Weights<TxType> = "Deposit=1, TRANSFER=3".parse().unwrap();
+        assert_eq!(types.share(TxType::Transfer), 0.75);
+        assert_eq!(types.share(TxType::Withdrawal), 0.0);
+        assert!("deposit=0".parse::<Weights<TxType>>().is_err());
+        assert_matches!("refund=1".parse::<Weights<TxType>>(), Err(_));
+
+        assert_eq!(time_range("2021-03"), Ok((1614556800000, 1617235200000)));
+        assert_eq!(
+            time_range("2021-03-01..2021-03-02"),
+            Ok((1614556800000, 1614643200000))
+        );
+        assert_eq!(time_range("1000..=2000"), Ok((1000, 2001)));
+        assert_eq!(
+            time_range("2021-03..=2021-04"),
+            Ok((1614556800000, 1619827200000))
+        );
+        assert!(time_range("2021-03..").is_err());
+    }
+
+    #[test]
+    fn test_generator_clamps_settings() {
+        let records: Vec<_> = Generator::new(5)
+            .amounts(Amounts::Uniform { min: 10, max: 5 })
+            .time_range(u64::MAX, 0)
+            .records(100)
+            .collect();
+        for record in &records {
+            assert_eq!(record.amount, 10);
+            assert_eq!(record.timestamp, u64::MAX - 1);
+        }
+
+        let generator = Generator::new(5).amounts(Amounts::LogUniform {
+            min: 0,
+            max: u64::MAX,
+        });
+        for record in generator.records(100) {
+            assert!(record.amount >= 1);
+        }
+    }
+}
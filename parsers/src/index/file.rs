@@ -0,0 +1,164 @@
+//! Формат файла индекса
+//!
+//! ```text
+//! MAGIC "YPBI" | SOURCE_LEN u64 | SOURCE_MTIME u64 | SOURCE_HASH u64 | COUNT u64
+//! COUNT раз: TX_ID u64 | OFFSET u64       - по возрастанию TX_ID
+//! COUNT раз: TIMESTAMP u64 | OFFSET u64   - по возрастанию TIMESTAMP
+//! ```
+//!
+//! Все числа big-endian, как в YPBankBin. SOURCE_* - признаки архива, см. [SourceStamp].
+//! Файл отображается в память, таблицы читаются двоичным поиском на месте.
+
+use super::{IndexError, SourceStamp};
+use memmap2::Mmap;
+use std::{
+    fs::File,
+    io::Write,
+    ops::{Deref, Range},
+};
+
+pub const INDEX_MAGIC: [u8; 4] = *b"YPBI";
+
+pub(super) const HEADER_SIZE: usize = 36;
+const ENTRY_SIZE: usize = 16;
+
+/// Байты индекса: построенные в памяти или отображение файла
+#[derive(Debug)]
+pub(super) enum IndexData {
+    Built(Vec<u8>),
+    Mapped(Mmap),
+}
+
+impl Deref for IndexData {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            IndexData::Built(bytes) => bytes,
+            IndexData::Mapped(map) => map,
+        }
+    }
+}
+
+/// Кодирует индекс, таблицы должны быть упорядочены
+pub(super) fn encode(source: SourceStamp, by_id: &[(u64, u64)], by_time: &[(u64, u64)]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(HEADER_SIZE + (by_id.len() + by_time.len()) * ENTRY_SIZE);
+    buf.extend_from_slice(&INDEX_MAGIC);
+    for value in [source.len, source.mtime, source.hash, by_id.len() as u64] {
+        buf.extend_from_slice(&value.to_be_bytes());
+    }
+    for &(key, offset) in by_id.iter().chain(by_time) {
+        buf.extend_from_slice(&key.to_be_bytes());
+        buf.extend_from_slice(&offset.to_be_bytes());
+    }
+    buf
+}
+
+pub(super) fn write<W: Write>(data: &[u8], out: &mut W) -> Result<(), IndexError> {
+    out.write_all(data)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Отображает файл индекса и проверяет заголовок. Порядок записей не проверяется:
+/// запись по смещению из индекса всё равно сверяется с архивом
+pub(super) fn map(file: &File) -> Result<IndexData, IndexError> {
+    // SAFETY: отображение только читается. Если файл индекса изменят, пока он открыт,
+    // поиск может вернуть неверное смещение - его поймает сверка записи с индексом.
+    let map = unsafe { Mmap::map(file)? };
+    check(&map)?;
+    Ok(IndexData::Mapped(map))
+}
+
+/// Проверяет MAGIC и то, что размер соответствует COUNT
+pub(super) fn check(data: &[u8]) -> Result<(), IndexError> {
+    if data.len() < HEADER_SIZE {
+        return Err(IndexError::InvalidIndex("файл короче заголовка".into()));
+    }
+    if data[..4] != INDEX_MAGIC {
+        return Err(IndexError::InvalidIndex("неверный MAGIC".into()));
+    }
+    let count = u64_at(data, 28);
+    let expected = usize::try_from(count)
+        .ok()
+        .and_then(|count| count.checked_mul(2 * ENTRY_SIZE))
+        .and_then(|len| len.checked_add(HEADER_SIZE));
+    if expected != Some(data.len()) {
+        return Err(IndexError::InvalidIndex(format!(
+            "{} байт не соответствуют {} записям",
+            data.len(),
+            count
+        )));
+    }
+    Ok(())
+}
+
+/// Признаки архива из заголовка проверенного индекса
+pub(super) fn source(data: &[u8]) -> SourceStamp {
+    SourceStamp {
+        len: u64_at(data, 4),
+        mtime: u64_at(data, 12),
+        hash: u64_at(data, 20),
+    }
+}
+
+/// Таблицы по TX_ID и по TIMESTAMP проверенного индекса
+pub(super) fn tables(data: &[u8]) -> (Entries<'_>, Entries<'_>) {
+    let (by_id, by_time) = data[HEADER_SIZE..].split_at((data.len() - HEADER_SIZE) / 2);
+    (Entries { bytes: by_id }, Entries { bytes: by_time })
+}
+
+fn u64_at(data: &[u8], pos: usize) -> u64 {
+    u64::from_be_bytes(data[pos..pos + 8].try_into().unwrap())
+}
+
+/// Упорядоченная таблица пар (ключ, смещение) прямо в байтах индекса
+#[derive(Debug, Clone, Copy)]
+pub struct Entries<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Entries<'a> {
+    pub fn len(&self) -> usize {
+        self.bytes.len() / ENTRY_SIZE
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Пара (ключ, смещение) с номером `index`
+    pub fn get(&self, index: usize) -> Option<(u64, u64)> {
+        let pos = index.checked_mul(ENTRY_SIZE)?;
+        (pos < self.bytes.len()).then(|| (u64_at(self.bytes, pos), u64_at(self.bytes, pos + 8)))
+    }
+
+    /// Номер первой пары, ключ которой не удовлетворяет `pred`, как у [slice::partition_point]
+    pub fn partition_point(&self, mut pred: impl FnMut(u64) -> bool) -> usize {
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if pred(u64_at(self.bytes, mid * ENTRY_SIZE)) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+
+    /// Пары с номерами из `range`
+    pub fn slice(&self, range: Range<usize>) -> Entries<'a> {
+        Entries {
+            bytes: &self.bytes[range.start * ENTRY_SIZE..range.end * ENTRY_SIZE],
+        }
+    }
+
+    pub fn iter(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (u64, u64)> + DoubleEndedIterator + use<'a> {
+        self.bytes
+            .chunks_exact(ENTRY_SIZE)
+            .map(|entry| (u64_at(entry, 0), u64_at(entry, 8)))
+    }
+}
@@ -0,0 +1,489 @@
+//! Индекс для произвольного доступа к файлам YPBankBin
+//!
+//! Индекс хранится рядом с архивом (`archive.bin.idx`, см. [BinIndex::sidecar_path]) и
+//! отображает TX_ID и TIMESTAMP в смещения записей, чтобы найти транзакцию
+//! или окно по времени без чтения всего файла. Файл индекса отображается в память
+//! и не разбирается при открытии: поиск идёт двоичным поиском прямо по его байтам.
+
+mod file;
+
+pub use file::{Entries, INDEX_MAGIC};
+
+use crate::{
+    errors::{ParseError, ParseErrorKind, ParseMode, ParseReport},
+    formats::{
+        RecordReader,
+        mmap::{BinMmap, BinSliceReader},
+    },
+    record::TxRecordRef,
+};
+use file::IndexData;
+use siphasher::sip::SipHasher24;
+use std::{
+    fmt::Display,
+    fs::File,
+    hash::Hasher,
+    io::{self, BufWriter, ErrorKind},
+    ops::{Bound, RangeBounds},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// Ошибки построения и использования индекса
+#[derive(Debug)]
+pub enum IndexError {
+    /// Ошибка чтения архива или файла индекса
+    Parse(ParseError),
+
+    /// Файл индекса не подходит под формат
+    InvalidIndex(String),
+
+    /// Индекс построен для другой версии архива
+    Stale {
+        indexed: SourceStamp,
+        actual: SourceStamp,
+    },
+
+    /// По смещению из индекса нет записи, на которую указывает индекс
+    Mismatch { offset: u64 },
+}
+
+impl Display for IndexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::Parse(err) => write!(f, "{}", err),
+            IndexError::InvalidIndex(msg) => write!(f, "неверный файл индекса: {}", msg),
+            IndexError::Stale { indexed, actual } if indexed.len != actual.len => write!(
+                f,
+                "индекс устарел: построен для {} байт, в архиве {} байт",
+                indexed.len, actual.len
+            ),
+            IndexError::Stale { .. } => {
+                write!(f, "индекс устарел: архив изменён после построения индекса")
+            }
+            IndexError::Mismatch { offset } => write!(
+                f,
+                "по смещению {} нет проиндексированной записи, индекс не соответствует архиву",
+                offset
+            ),
+        }
+    }
+}
+
+impl std::error::Error for IndexError {}
+
+impl From<ParseError> for IndexError {
+    fn from(err: ParseError) -> Self {
+        IndexError::Parse(err)
+    }
+}
+
+impl From<io::Error> for IndexError {
+    fn from(err: io::Error) -> Self {
+        IndexError::Parse(err.into())
+    }
+}
+
+/// Сколько байт с начала и с конца архива входит в [SourceStamp::hash]
+const STAMP_SAMPLE: usize = 4096;
+
+/// Признаки архива, по которым индекс сверяется с ним
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceStamp {
+    /// Размер в байтах
+    pub len: u64,
+    /// Время изменения файла в наносекундах Unix, `0` - неизвестно и не сверяется
+    pub mtime: u64,
+    /// SipHash первых и последних [STAMP_SAMPLE] байт: заголовок файла и последние записи
+    pub hash: u64,
+}
+
+impl SourceStamp {
+    /// Признаки содержимого `data` файла, изменённого в `mtime`
+    pub fn new(data: &[u8], mtime: Option<SystemTime>) -> Self {
+        let mut hasher = SipHasher24::new();
+        hasher.write(&data[..data.len().min(STAMP_SAMPLE)]);
+        hasher.write(&data[data.len().saturating_sub(STAMP_SAMPLE)..]);
+        let mtime = mtime
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map_or(0, |since| {
+                u64::try_from(since.as_nanos()).unwrap_or(u64::MAX)
+            });
+        Self {
+            len: data.len() as u64,
+            mtime,
+            hash: hasher.finish(),
+        }
+    }
+
+    /// Признаки открытого файла архива с содержимым `data`
+    fn of_file(file: &File, data: &[u8]) -> io::Result<Self> {
+        Ok(Self::new(data, file.metadata()?.modified().ok()))
+    }
+
+    /// Тот же архив: время изменения сверяется, только если известно обоим
+    fn matches(&self, other: &SourceStamp) -> bool {
+        self.len == other.len
+            && self.hash == other.hash
+            && (self.mtime == 0 || other.mtime == 0 || self.mtime == other.mtime)
+    }
+}
+
+/// Индекс архива YPBankBin: смещения записей по TX_ID и по TIMESTAMP
+///
+/// Байты индекса в формате файла, см. [INDEX_MAGIC]: загруженный индекс
+/// отображён в память, построенный хранится в памяти так же.
+#[derive(Debug)]
+pub struct BinIndex {
+    data: IndexData,
+}
+
+impl PartialEq for BinIndex {
+    fn eq(&self, other: &Self) -> bool {
+        self.data[..] == other.data[..]
+    }
+}
+
+impl Eq for BinIndex {}
+
+impl BinIndex {
+    /// Строит индекс по содержимому архива, первая ошибка прерывает построение
+    pub fn build(data: &[u8]) -> Result<Self, ParseError> {
+        Self::build_with(data, ParseMode::Strict).map(|report| report.data)
+    }
+
+    /// Строит индекс, в нестрогом режиме испорченные записи в индекс не попадают.
+    /// Время изменения архива неизвестно, см. [SourceStamp::mtime]
+    pub fn build_with(data: &[u8], mode: ParseMode) -> Result<ParseReport<Self>, ParseError> {
+        Self::build_for(data, SourceStamp::new(data, None), mode)
+    }
+
+    fn build_for(
+        data: &[u8],
+        source: SourceStamp,
+        mode: ParseMode,
+    ) -> Result<ParseReport<Self>, ParseError> {
+        let mut reader = BinSliceReader::with_mode(data, mode);
+        let mut by_id = Vec::new();
+        let mut by_time = Vec::new();
+        while let Some(record) = reader.read_ref()? {
+            let offset = reader.record_offset();
+            by_id.push((record.tx_id, offset));
+            by_time.push((record.timestamp, offset));
+        }
+        by_id.sort_unstable();
+        by_time.sort_unstable();
+
+        Ok(ParseReport {
+            data: Self {
+                data: IndexData::Built(file::encode(source, &by_id, &by_time)),
+            },
+            errors: reader.take_errors(),
+        })
+    }
+
+    /// Путь индекса рядом с архивом: `archive.bin` - `archive.bin.idx`
+    pub fn sidecar_path(path: impl AsRef<Path>) -> PathBuf {
+        let mut path = path.as_ref().as_os_str().to_owned();
+        path.push(".idx");
+        path.into()
+    }
+
+    /// Отображает файл индекса в память, проверяются только заголовок и размер
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, IndexError> {
+        let data = file::map(&File::open(path)?)?;
+        Ok(Self { data })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), IndexError> {
+        file::write(&self.data, &mut BufWriter::new(File::create(path)?))
+    }
+
+    /// Признаки архива, для которого построен индекс
+    pub fn source(&self) -> SourceStamp {
+        file::source(&self.data)
+    }
+
+    /// Проверяет, что индекс построен для архива с признаками `actual`
+    pub fn check(&self, actual: SourceStamp) -> Result<(), IndexError> {
+        let indexed = self.source();
+        if !indexed.matches(&actual) {
+            return Err(IndexError::Stale { indexed, actual });
+        }
+        Ok(())
+    }
+
+    /// Количество проиндексированных записей
+    pub fn len(&self) -> usize {
+        self.by_id().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_id().is_empty()
+    }
+
+    /// Пары (TX_ID, смещение) по возрастанию
+    fn by_id(&self) -> Entries<'_> {
+        file::tables(&self.data).0
+    }
+
+    /// Пары (TIMESTAMP, смещение) по возрастанию
+    fn by_time(&self) -> Entries<'_> {
+        file::tables(&self.data).1
+    }
+
+    /// Смещение первой записи с TX_ID
+    pub fn offset(&self, tx_id: u64) -> Option<u64> {
+        let by_id = self.by_id();
+        let pos = by_id.partition_point(|id| id < tx_id);
+        by_id
+            .get(pos)
+            .filter(|&(id, _)| id == tx_id)
+            .map(|(_, offset)| offset)
+    }
+
+    /// Пары (TIMESTAMP, смещение) записей из диапазона времени, по возрастанию TIMESTAMP
+    pub fn window(&self, range: impl RangeBounds<u64>) -> Entries<'_> {
+        let by_time = self.by_time();
+        let start = match range.start_bound() {
+            Bound::Included(&t) => by_time.partition_point(|ts| ts < t),
+            Bound::Excluded(&t) => by_time.partition_point(|ts| ts <= t),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&t) => by_time.partition_point(|ts| ts <= t),
+            Bound::Excluded(&t) => by_time.partition_point(|ts| ts < t),
+            Bound::Unbounded => by_time.len(),
+        };
+        by_time.slice(start..end.max(start))
+    }
+}
+
+/// Архив YPBankBin, отображённый в память, вместе с индексом
+#[derive(Debug)]
+pub struct IndexedBin {
+    file: BinMmap,
+    index: BinIndex,
+}
+
+impl IndexedBin {
+    /// Открывает архив с готовым индексом рядом
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, IndexError> {
+        let (file, source) = Self::map(&path)?;
+        let index = BinIndex::load(BinIndex::sidecar_path(&path))?;
+        index.check(source)?;
+        Ok(Self { file, index })
+    }
+
+    /// Открывает архив, индекс строится и сохраняется, если его нет, он испорчен или устарел.
+    /// Индекс строится в нестрогом режиме: испорченные записи в него не попадают,
+    /// их ошибки - в отчёте. У загруженного готового индекса отчёт пуст
+    pub fn open_or_build(path: impl AsRef<Path>) -> Result<ParseReport<Self>, IndexError> {
+        let (file, source) = Self::map(&path)?;
+        let sidecar = BinIndex::sidecar_path(&path);
+        let loaded = BinIndex::load(&sidecar).and_then(|index| {
+            index.check(source)?;
+            Ok(index)
+        });
+        let (index, errors) = match loaded {
+            Ok(index) => (index, Vec::new()),
+            Err(IndexError::Parse(ParseError {
+                kind: ParseErrorKind::Io(err),
+                ..
+            })) if err.kind() != ErrorKind::NotFound => return Err(err.into()),
+            Err(_) => {
+                let report = BinIndex::build_for(file.as_bytes(), source, ParseMode::Lenient)?;
+                report.data.save(&sidecar)?;
+                (report.data, report.errors)
+            }
+        };
+        Ok(ParseReport {
+            data: Self { file, index },
+            errors,
+        })
+    }
+
+    /// Отображает архив и снимает его признаки
+    fn map(path: impl AsRef<Path>) -> Result<(BinMmap, SourceStamp), IndexError> {
+        let archive = File::open(path)?;
+        let file = BinMmap::from_file(&archive)?;
+        let source = SourceStamp::of_file(&archive, file.as_bytes())?;
+        Ok((file, source))
+    }
+
+    pub fn index(&self) -> &BinIndex {
+        &self.index
+    }
+
+    /// Запись с TX_ID, `None` - такой записи в архиве нет
+    pub fn get(&self, tx_id: u64) -> Result<Option<TxRecordRef<'_>>, IndexError> {
+        self.index
+            .offset(tx_id)
+            .map(|offset| self.record_at(offset, |record| record.tx_id == tx_id))
+            .transpose()
+    }
+
+    /// Записи из диапазона времени по возрастанию TIMESTAMP
+    pub fn window(
+        &self,
+        range: impl RangeBounds<u64>,
+    ) -> impl Iterator<Item = Result<TxRecordRef<'_>, IndexError>> + '_ {
+        self.index.window(range).iter().map(|(timestamp, offset)| {
+            self.record_at(offset, |record| record.timestamp == timestamp)
+        })
+    }
+
+    /// Читает запись по смещению и сверяет её с индексом
+    fn record_at(
+        &self,
+        offset: u64,
+        expected: impl Fn(&TxRecordRef) -> bool,
+    ) -> Result<TxRecordRef<'_>, IndexError> {
+        let mut reader = self.file.records();
+        reader.seek(offset);
+        match reader.read_ref()? {
+            Some(record) if expected(&record) => Ok(record),
+            _ => Err(IndexError::Mismatch { offset }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::binary::BinReader;
+    use assert_matches::assert_matches;
+
+    const EXAMPLE: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("ypbank-index-{}-{}.bin", name, std::process::id()))
+    }
+
+    #[test]
+    fn test_index_lookup_and_window() {
+        let records = BinReader::new(EXAMPLE).read_all().unwrap();
+        let index = BinIndex::build(EXAMPLE).unwrap();
+        assert_eq!(index.len(), records.len());
+
+        let mut reader = BinSliceReader::new(EXAMPLE);
+        for record in &records {
+            reader.seek(index.offset(record.tx_id).unwrap());
+            assert_eq!(reader.read_ref().unwrap().unwrap(), record.to_ref());
+        }
+        assert_eq!(index.offset(1), None);
+
+        let mut timestamps: Vec<u64> = records.iter().map(|r| r.timestamp).collect();
+        timestamps.sort_unstable();
+        let (from, to) = (timestamps[100], timestamps[199]);
+        let window = index.window(from..=to);
+        let expected = timestamps.iter().filter(|&&t| (from..=to).contains(&t));
+        assert!(window.iter().map(|(t, _)| t).eq(expected.copied()));
+        assert!(index.window(to..from).is_empty());
+        assert_eq!(index.window(..).len(), records.len());
+    }
+
+    #[test]
+    fn test_index_file_round_trip() {
+        let index = BinIndex::build(EXAMPLE).unwrap();
+        let path = temp_path("file");
+        index.save(&path).unwrap();
+        let loaded = BinIndex::load(&path).unwrap();
+        assert_matches!(loaded.data, IndexData::Mapped(_));
+        assert_eq!(loaded, index);
+        assert_eq!(loaded.source(), SourceStamp::new(EXAMPLE, None));
+
+        let mut data = std::fs::read(&path).unwrap();
+        assert_eq!(data.len(), file::HEADER_SIZE + 1000 * 32);
+        assert_matches!(
+            file::check(&data[..data.len() - 1]),
+            Err(IndexError::InvalidIndex(_))
+        );
+        data[0] = b'X';
+        std::fs::write(&path, &data).unwrap();
+        assert_matches!(BinIndex::load(&path), Err(IndexError::InvalidIndex(_)));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_indexed_bin_open() {
+        let path = temp_path("open");
+        let sidecar = BinIndex::sidecar_path(&path);
+        std::fs::write(&path, EXAMPLE).unwrap();
+        let _ = std::fs::remove_file(&sidecar);
+
+        assert_matches!(IndexedBin::open(&path), Err(IndexError::Parse(err)) if err.is_io());
+        let report = IndexedBin::open_or_build(&path).unwrap();
+        assert!(report.is_clean());
+        let archive = report.data;
+        assert!(sidecar.exists());
+        assert_ne!(archive.index().source().mtime, 0);
+
+        let first = archive.get(1000000000000000).unwrap().unwrap();
+        assert_eq!(
+            first.into_owned(),
+            BinReader::new(EXAMPLE).read_record().unwrap().unwrap()
+        );
+        assert_eq!(archive.get(1).unwrap(), None);
+        let window: Vec<_> = archive.window(..).map(Result::unwrap).collect();
+        assert_eq!(window.len(), 1000);
+        assert!(window.is_sorted_by_key(|r| r.timestamp));
+        drop(archive);
+
+        // Архив дописали целой записью и её обрывком - индекс устарел и строится заново,
+        // ошибка обрывка - в отчёте
+        let mut longer = EXAMPLE.to_vec();
+        longer.extend_from_slice(&EXAMPLE[..100]);
+        std::fs::write(&path, &longer).unwrap();
+        assert_matches!(
+            IndexedBin::open(&path),
+            Err(IndexError::Stale { indexed, .. }) if indexed.len == EXAMPLE.len() as u64
+        );
+        let report = IndexedBin::open_or_build(&path).unwrap();
+        assert!(!report.is_clean());
+        assert_eq!(report.data.index().len(), 1001);
+        drop(report);
+
+        // Архив перезаписали с тем же размером и временем изменения: начало файла
+        // сверяется по хешу, а изменённая запись в середине - при чтении
+        std::fs::write(&path, EXAMPLE).unwrap();
+        let archive = IndexedBin::open_or_build(&path).unwrap().data;
+        let records = BinReader::new(EXAMPLE).read_all().unwrap();
+        let middle = &records[500];
+        let offset = archive.index().offset(middle.tx_id).unwrap();
+        drop(archive);
+        let rewrite = |data: &[u8]| {
+            let mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+            std::fs::write(&path, data).unwrap();
+            let file = File::options().write(true).open(&path).unwrap();
+            file.set_modified(mtime).unwrap();
+        };
+
+        let mut changed = EXAMPLE.to_vec();
+        changed[offset as usize + 8 + 7] ^= 1;
+        rewrite(&changed);
+        let archive = IndexedBin::open(&path).unwrap();
+        assert_matches!(
+            archive.get(middle.tx_id),
+            Err(IndexError::Mismatch { offset: o }) if o == offset
+        );
+        drop(archive);
+
+        let mut changed = EXAMPLE.to_vec();
+        changed[16 + 8 + 7] ^= 1;
+        rewrite(&changed);
+        let err = IndexedBin::open(&path).unwrap_err();
+        assert_matches!(err, IndexError::Stale { .. });
+        assert_eq!(
+            err.to_string(),
+            "индекс устарел: архив изменён после построения индекса"
+        );
+
+        // Тот же архив, но изменённый позже индекса
+        std::fs::write(&path, EXAMPLE).unwrap();
+        assert_matches!(IndexedBin::open(&path), Err(IndexError::Stale { .. }));
+
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&sidecar).unwrap();
+    }
+}
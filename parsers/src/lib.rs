@@ -1 +1,17 @@
+pub mod anonymise;
+pub mod cli;
+pub mod compare;
+pub mod compress;
+pub mod errors;
+pub mod formats;
+pub mod generate;
+pub mod index;
+pub mod merge;
+pub mod query;
+pub mod record;
+pub mod split;
+pub mod validate;
 
+pub use errors::{Line, ParseError, ParseErrorKind, ParseMode, ParseReport, Position, Snippet};
+pub use formats::{Format, RecordReader, RecordWriter, detect_format};
+pub use record::{TxRecord, TxRecordRef, TxStatus, TxType};
@@ -0,0 +1,335 @@
+//! Слияние файлов YPBank в один, отсортированный по TIMESTAMP
+//!
+//! Входы могут быть в любых форматах и пересекаться. Из записей с одним TX_ID
+//! остаётся первая по порядку входов, остальные отбрасываются. Если отброшенная
+//! запись отличается от оставленной, это конфликт - он считается в [MergeReport],
+//! а первые [Merger::max_conflicts] конфликтов сохраняются целиком.
+//!
+//! Данные могут не помещаться в память, поэтому используется внешняя сортировка:
+//! сначала по TX_ID, чтобы найти повторы, затем по TIMESTAMP. Всё, что не помещается
+//! в бюджет памяти, сбрасывается во временные файлы YPBankBin.
+
+mod sort;
+
+use crate::{
+    compare::{FieldMismatch, field_values},
+    errors::ParseError,
+    formats::{RecordReader, RecordWriter},
+    record::TxRecord,
+};
+use sort::ExternalSort;
+use std::{fmt::Display, path::PathBuf};
+
+/// Бюджет памяти по умолчанию
+pub const DEFAULT_MEMORY_BUDGET: usize = 256 << 20;
+
+/// Сколько конфликтов сохранять по умолчанию
+pub const DEFAULT_MAX_CONFLICTS: usize = 1000;
+
+/// Ошибки слияния
+#[derive(Debug)]
+pub enum MergeError {
+    /// Ошибка чтения входа номер `input`, с 0
+    Input { input: usize, error: ParseError },
+
+    /// Ошибка временных файлов
+    Temp(ParseError),
+
+    /// Ошибка записи результата
+    Output(ParseError),
+}
+
+impl Display for MergeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MergeError::Input { input, error } => write!(f, "вход {}: {}", input + 1, error),
+            MergeError::Temp(err) => write!(f, "временный файл: {}", err),
+            MergeError::Output(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// Записи с одним TX_ID и разными полями
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    /// Первая по порядку входов запись, она попала в результат
+    pub kept: TxRecord,
+    pub dropped: TxRecord,
+}
+
+impl Conflict {
+    pub fn tx_id(&self) -> u64 {
+        self.kept.tx_id
+    }
+
+    /// Различающиеся поля: `left` - у оставленной записи, `right` - у отброшенной
+    pub fn mismatches(&self) -> Vec<FieldMismatch> {
+        field_values(&self.kept)
+            .into_iter()
+            .zip(field_values(&self.dropped))
+            .filter(|((_, kept), (_, dropped))| kept != dropped)
+            .map(|((field, left), (_, right))| FieldMismatch {
+                tx_id: self.tx_id(),
+                field,
+                left,
+                right,
+            })
+            .collect()
+    }
+}
+
+/// Итог слияния
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct MergeReport {
+    /// Прочитано записей из всех входов
+    pub read: u64,
+    pub written: u64,
+    /// Отброшено точных повторов
+    pub duplicates: u64,
+    /// Первые [Merger::max_conflicts] отброшенных повторов с другими полями
+    pub conflicts: Vec<Conflict>,
+    /// Всего конфликтов, включая не сохранённые в `conflicts`
+    pub conflict_count: u64,
+    /// Сколько прогонов сортировки записано во временные файлы
+    pub spilled_runs: usize,
+}
+
+/// Слияние с внешней сортировкой
+///
+/// ```no_run
+/// use parsers::{Format, compress, merge::Merger};
+///
+/// let mut inputs = vec![
+///     Format::Csv.reader(compress::open("monday.csv")?),
+///     Format::Bin.reader(compress::open("tuesday.bin.gz")?),
+/// ];
+/// let mut output = Format::Bin.writer(compress::create("week.bin")?);
+/// let report = Merger::new()
+///     .memory_budget(64 << 20)
+///     .merge(&mut inputs, &mut output)?;
+/// output.finish()?;
+/// println!("конфликтов: {}", report.conflict_count);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Merger {
+    memory_budget: usize,
+    temp_dir: PathBuf,
+    max_conflicts: usize,
+}
+
+impl Default for Merger {
+    fn default() -> Self {
+        Self {
+            memory_budget: DEFAULT_MEMORY_BUDGET,
+            temp_dir: std::env::temp_dir(),
+            max_conflicts: DEFAULT_MAX_CONFLICTS,
+        }
+    }
+}
+
+impl Merger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Примерный объём памяти под записи в байтах. Сверх него на каждый
+    /// открытый временный файл нужен буфер чтения около 72 КиБ
+    pub fn memory_budget(mut self, bytes: usize) -> Self {
+        self.memory_budget = bytes;
+        self
+    }
+
+    /// Каталог временных файлов, по умолчанию системный
+    pub fn temp_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = dir.into();
+        self
+    }
+
+    /// Сколько конфликтов хранить в [MergeReport::conflicts]. Каждый держит в памяти
+    /// две записи, поэтому остальные только считаются в [MergeReport::conflict_count]
+    pub fn max_conflicts(mut self, conflicts: usize) -> Self {
+        self.max_conflicts = conflicts;
+        self
+    }
+
+    /// Сливает `inputs` в `output` в порядке TIMESTAMP, при равном времени - по TX_ID.
+    ///
+    /// Ошибки пропущенных в нестрогом режиме записей остаются во входах, см. [RecordReader::take_errors].
+    /// `output` не завершается, [RecordWriter::finish] вызывает владелец.
+    pub fn merge<W: RecordWriter + ?Sized>(
+        &self,
+        inputs: &mut [Box<dyn RecordReader + '_>],
+        output: &mut W,
+    ) -> Result<MergeReport, MergeError> {
+        // Обе сортировки держат записи в памяти одновременно
+        let budget = self.memory_budget / 2;
+        let mut report = MergeReport::default();
+
+        let mut by_id = ExternalSort::new(|r| (r.tx_id, 0), budget, &self.temp_dir);
+        for (input, reader) in inputs.iter_mut().enumerate() {
+            let input_error = |error| MergeError::Input { input, error };
+            while let Some(record) = reader.read_record().map_err(input_error)? {
+                report.read += 1;
+                by_id.push(record).map_err(MergeError::Temp)?;
+            }
+        }
+        report.spilled_runs += by_id.spilled();
+
+        let mut by_time = ExternalSort::new(|r| (r.timestamp, r.tx_id), budget, &self.temp_dir);
+        let mut kept: Option<TxRecord> = None;
+        let by_id = by_id.finish().map_err(MergeError::Temp)?;
+        for record in by_id {
+            let record = record.map_err(MergeError::Temp)?;
+            match &kept {
+                Some(first) if first.tx_id == record.tx_id => {
+                    if *first == record {
+                        report.duplicates += 1;
+                    } else {
+                        report.conflict_count += 1;
+                        if report.conflicts.len() < self.max_conflicts {
+                            report.conflicts.push(Conflict {
+                                kept: first.clone(),
+                                dropped: record,
+                            });
+                        }
+                    }
+                }
+                _ => {
+                    if let Some(first) = kept.replace(record) {
+                        by_time.push(first).map_err(MergeError::Temp)?;
+                    }
+                }
+            }
+        }
+        if let Some(last) = kept {
+            by_time.push(last).map_err(MergeError::Temp)?;
+        }
+        report.spilled_runs += by_time.spilled();
+
+        for record in by_time.finish().map_err(MergeError::Temp)? {
+            let record = record.map_err(MergeError::Temp)?;
+            output.write_record(&record).map_err(MergeError::Output)?;
+            report.written += 1;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Format, TxStatus,
+        formats::{
+            binary::{BinReader, BinWriter},
+            csv::CsvWriter,
+        },
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    fn encode(format: Format, records: &[TxRecord]) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut writer = format.writer(&mut data);
+        writer.write_all(records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        data
+    }
+
+    #[test]
+    fn test_merge_overlapping_dumps() {
+        let records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        // Три пересекающихся выгрузки в разных форматах, первая - в обратном порядке времени
+        let mut first = records[..600].to_vec();
+        first.reverse();
+        let second = encode(Format::Text, &records[400..]);
+        let mut third = records[100..200].to_vec();
+        third[5].status = TxStatus::Success;
+        third[6].amount += 1;
+
+        let first = encode(Format::Bin, &first);
+        let third = encode(Format::Json, &third);
+        let mut inputs = vec![
+            Format::Bin.reader(first.as_slice()),
+            Format::Text.reader(second.as_slice()),
+            Format::Json.reader(third.as_slice()),
+        ];
+
+        let dir = std::env::temp_dir().join(format!("ypbank-merge-test-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut output = BinWriter::new(Vec::new());
+        let report = Merger::new()
+            .memory_budget(16 * 1024)
+            .temp_dir(&dir)
+            .merge(&mut inputs, &mut output)
+            .unwrap();
+        let merged = output.into_inner().unwrap();
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+        std::fs::remove_dir(&dir).unwrap();
+
+        let mut expected = records.clone();
+        expected.sort_by_key(|r| (r.timestamp, r.tx_id));
+        assert_eq!(
+            BinReader::new(merged.as_slice()).read_all().unwrap(),
+            expected
+        );
+        assert_eq!(report.read, 600 + 600 + 100);
+        assert_eq!(report.written, 1000);
+        assert_eq!(report.duplicates, 200 + 98);
+        assert!(report.spilled_runs > 1);
+
+        assert_eq!(report.conflicts.len(), 2);
+        assert_eq!(report.conflict_count, 2);
+        let conflict = &report.conflicts[0];
+        assert_eq!(conflict.kept, records[105]);
+        let mismatches = conflict.mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].field, "STATUS");
+        assert_eq!(report.conflicts[1].mismatches()[0].field, "AMOUNT");
+    }
+
+    #[test]
+    fn test_merge_max_conflicts() {
+        let records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        let mut changed = records[..10].to_vec();
+        for record in &mut changed {
+            record.amount += 1;
+        }
+        let second = encode(Format::Bin, &changed);
+        let mut inputs = vec![
+            Format::Bin.reader(EXAMPLE_BIN),
+            Format::Bin.reader(second.as_slice()),
+        ];
+        let mut output = BinWriter::new(Vec::new());
+        let report = Merger::new()
+            .max_conflicts(3)
+            .merge(&mut inputs, &mut output)
+            .unwrap();
+        assert_eq!(report.written, 1000);
+        assert_eq!(report.conflict_count, 10);
+        assert_eq!(report.conflicts.len(), 3);
+        assert!(
+            report
+                .conflicts
+                .iter()
+                .all(|conflict| changed.contains(&conflict.dropped))
+        );
+    }
+
+    #[test]
+    fn test_merge_input_error() {
+        let mut inputs = vec![
+            Format::Bin.reader(EXAMPLE_BIN),
+            Format::Csv.reader("TX_ID\n".as_bytes()),
+        ];
+        let mut output = CsvWriter::new(Vec::new());
+        let err = Merger::new().merge(&mut inputs, &mut output).unwrap_err();
+        assert_matches!(err, MergeError::Input { input: 1, .. });
+        assert!(err.to_string().starts_with("вход 2: "));
+    }
+}
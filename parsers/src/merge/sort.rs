@@ -0,0 +1,296 @@
+//! Внешняя сортировка записей
+//!
+//! Записи копятся в памяти до бюджета, затем сортируются и сбрасываются во временный
+//! файл YPBankBin - прогон. Прогоны сливаются через кучу, не более [MAX_FAN_IN] за раз.
+//! Записи с равным ключом выходят в порядке добавления.
+
+use crate::{
+    errors::ParseError,
+    formats::{RecordReader, RecordWriter, binary::BinReader, binary::BinWriter},
+    record::TxRecord,
+};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BinaryHeap,
+    fs::{self, File},
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering as AtomicOrdering},
+};
+
+/// Ключ сортировки
+pub(crate) type Key = (u64, u64);
+
+/// Сколько прогонов сливается за раз. У каждого открытого прогона свой буфер чтения
+pub(crate) const MAX_FAN_IN: usize = 64;
+
+/// Счётчик имён временных файлов в пределах процесса
+static RUN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Примерный размер записи в памяти
+fn record_size(record: &TxRecord) -> usize {
+    size_of::<TxRecord>() + record.description.len()
+}
+
+/// Временный файл с отсортированными записями, удаляется вместе со значением
+struct Run {
+    path: PathBuf,
+}
+
+impl Run {
+    fn write(
+        dir: &Path,
+        records: impl Iterator<Item = Result<TxRecord, ParseError>>,
+    ) -> Result<Self, ParseError> {
+        let name = format!(
+            "ypbank-sort-{}-{}.run",
+            std::process::id(),
+            RUN_COUNTER.fetch_add(1, AtomicOrdering::Relaxed)
+        );
+        let run = Run {
+            path: dir.join(name),
+        };
+        let mut writer = BinWriter::new(BufWriter::new(File::create_new(&run.path)?));
+        for record in records {
+            writer.write_record(&record?)?;
+        }
+        writer.finish()?;
+        Ok(run)
+    }
+
+    fn open(&self) -> Result<BinReader<BufReader<File>>, ParseError> {
+        Ok(BinReader::new(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Отсортированная последовательность записей
+enum Source {
+    Run {
+        reader: BinReader<BufReader<File>>,
+        /// Прогон хранится, пока читается, чтобы файл не удалили раньше
+        _run: Run,
+    },
+    Memory(std::vec::IntoIter<TxRecord>),
+}
+
+impl Source {
+    fn next(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        match self {
+            Source::Run { reader, .. } => reader.read_record(),
+            Source::Memory(records) => Ok(records.next()),
+        }
+    }
+}
+
+/// Первая ещё не выданная запись источника
+struct Head {
+    key: Key,
+    source: usize,
+    record: TxRecord,
+}
+
+/// При равных ключах раньше идёт источник с меньшим номером - добавленный раньше
+impl Ord for Head {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.key, self.source).cmp(&(other.key, other.source))
+    }
+}
+
+impl PartialOrd for Head {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Head {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Head {}
+
+/// Слияние отсортированных источников, итератор по записям в порядке ключа
+pub(crate) struct Merged {
+    key: fn(&TxRecord) -> Key,
+    sources: Vec<Source>,
+    heap: BinaryHeap<Reverse<Head>>,
+    failed: bool,
+}
+
+impl Merged {
+    fn new(key: fn(&TxRecord) -> Key, mut sources: Vec<Source>) -> Result<Self, ParseError> {
+        let mut heap = BinaryHeap::with_capacity(sources.len());
+        for (source, records) in sources.iter_mut().enumerate() {
+            if let Some(record) = records.next()? {
+                heap.push(Reverse(Head {
+                    key: key(&record),
+                    source,
+                    record,
+                }));
+            }
+        }
+        Ok(Self {
+            key,
+            sources,
+            heap,
+            failed: false,
+        })
+    }
+
+    fn try_next(&mut self) -> Result<Option<TxRecord>, ParseError> {
+        let Some(Reverse(head)) = self.heap.pop() else {
+            return Ok(None);
+        };
+        if let Some(record) = self.sources[head.source].next()? {
+            self.heap.push(Reverse(Head {
+                key: (self.key)(&record),
+                source: head.source,
+                record,
+            }));
+        }
+        Ok(Some(head.record))
+    }
+}
+
+impl Iterator for Merged {
+    type Item = Result<TxRecord, ParseError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        match self.try_next() {
+            Ok(record) => record.map(Ok),
+            Err(err) => {
+                self.failed = true;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Сортировка с ограниченной памятью
+pub(crate) struct ExternalSort {
+    key: fn(&TxRecord) -> Key,
+    budget: usize,
+    dir: PathBuf,
+    buf: Vec<TxRecord>,
+    used: usize,
+    runs: Vec<Run>,
+    spilled: usize,
+}
+
+impl ExternalSort {
+    /// `budget` - байт на записи в памяти, `dir` - каталог временных файлов
+    pub fn new(key: fn(&TxRecord) -> Key, budget: usize, dir: &Path) -> Self {
+        Self {
+            key,
+            budget,
+            dir: dir.to_path_buf(),
+            buf: Vec::new(),
+            used: 0,
+            runs: Vec::new(),
+            spilled: 0,
+        }
+    }
+
+    pub fn push(&mut self, record: TxRecord) -> Result<(), ParseError> {
+        self.used += record_size(&record);
+        self.buf.push(record);
+        if self.used >= self.budget {
+            let records = self.take_sorted();
+            self.runs
+                .push(Run::write(&self.dir, records.into_iter().map(Ok))?);
+            self.spilled += 1;
+        }
+        Ok(())
+    }
+
+    /// Сколько прогонов сброшено на диск
+    pub fn spilled(&self) -> usize {
+        self.spilled
+    }
+
+    fn take_sorted(&mut self) -> Vec<TxRecord> {
+        let mut records = std::mem::take(&mut self.buf);
+        self.used = 0;
+        // Сортировка устойчивая: равные ключи остаются в порядке добавления
+        records.sort_by_key(self.key);
+        records
+    }
+
+    fn open(runs: Vec<Run>) -> Result<Vec<Source>, ParseError> {
+        runs.into_iter()
+            .map(|run| {
+                let reader = run.open()?;
+                Ok(Source::Run { reader, _run: run })
+            })
+            .collect()
+    }
+
+    /// Все записи в порядке ключа
+    pub fn finish(mut self) -> Result<Merged, ParseError> {
+        // Прогоны сверх MAX_FAN_IN сливаются проходами по группам соседних прогонов,
+        // чтобы порядок равных ключей сохранился. Ещё один источник - записи в памяти
+        while self.runs.len() >= MAX_FAN_IN {
+            let mut runs = std::mem::take(&mut self.runs).into_iter();
+            loop {
+                let group: Vec<Run> = runs.by_ref().take(MAX_FAN_IN).collect();
+                match group.len() {
+                    0 => break,
+                    1 => self.runs.extend(group),
+                    _ => {
+                        let merged = Merged::new(self.key, Self::open(group)?)?;
+                        self.runs.push(Run::write(&self.dir, merged)?);
+                        self.spilled += 1;
+                    }
+                }
+            }
+        }
+        let memory = self.take_sorted();
+        let mut sources = Self::open(std::mem::take(&mut self.runs))?;
+        sources.push(Source::Memory(memory.into_iter()));
+        Merged::new(self.key, sources)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::{TxType, testing::record};
+
+    #[test]
+    fn test_external_sort_spills_and_keeps_order() {
+        let dir = std::env::temp_dir().join(format!("ypbank-sort-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        // Бюджет меньше одной записи: каждая запись - отдельный прогон
+        let mut sort = ExternalSort::new(|r| (r.timestamp, 0), 1, &dir);
+        let records: Vec<TxRecord> = (0..200)
+            .map(|i| TxRecord {
+                timestamp: (i * 7919) % 50,
+                ..record(i, TxType::Deposit, 0, 1, 100)
+            })
+            .collect();
+        for record in records.clone() {
+            sort.push(record).unwrap();
+        }
+        let records_spilled = sort.spilled();
+        let sorted: Vec<TxRecord> = sort.finish().unwrap().map(Result::unwrap).collect();
+
+        let mut expected = records;
+        expected.sort_by_key(|r| r.timestamp);
+        assert_eq!(sorted, expected);
+
+        // Прогонов больше MAX_FAN_IN, все временные файлы удалены
+        assert!(records_spilled > MAX_FAN_IN);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 0);
+        fs::remove_dir(&dir).unwrap();
+    }
+}
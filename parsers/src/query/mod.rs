@@ -0,0 +1,283 @@
+//! Отбор записей по условиям: пользователь, тип, статус, сумма, время, описание
+//!
+//! Условия объединяются через И. Внутри списка типов или статусов - через ИЛИ.
+
+pub(crate) mod terms;
+
+use crate::record::{TxRecord, TxRecordRef, TxStatus, TxType};
+use regex::Regex;
+use std::{
+    fmt::Display,
+    ops::{Bound, RangeBounds},
+};
+
+/// Условия, которые понимает [Query::term]
+pub const TERMS: [&str; 8] = [
+    "user",
+    "from-user",
+    "to-user",
+    "type",
+    "status",
+    "amount",
+    "time",
+    "desc",
+];
+
+/// Ошибка разбора условия
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// Условия с таким именем нет, см. [TERMS]
+    UnknownTerm(String),
+
+    InvalidValue {
+        term: String,
+        value: String,
+        reason: String,
+    },
+}
+
+impl Display for QueryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryError::UnknownTerm(term) => write!(f, "неизвестное условие: {}", term),
+            QueryError::InvalidValue {
+                term,
+                value,
+                reason,
+            } => write!(f, "неверное условие {} {:?}: {}", term, value, reason),
+        }
+    }
+}
+
+impl std::error::Error for QueryError {}
+
+/// Фильтр записей
+///
+/// ```
+/// use parsers::{TxStatus, TxType, query::Query};
+///
+/// // Все неуспешные переводы пользователя 42 в марте 2021
+/// let query = Query::new()
+///     .user(42)
+///     .tx_type(TxType::Transfer)
+///     .status(TxStatus::Failure)
+///     .term("time", "2021-03")
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Query {
+    user: Option<u64>,
+    from_user: Option<u64>,
+    to_user: Option<u64>,
+    types: Vec<TxType>,
+    statuses: Vec<TxStatus>,
+    amount: terms::Range<i64>,
+    time: terms::Range<u64>,
+    description: Option<Regex>,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            user: None,
+            from_user: None,
+            to_user: None,
+            types: Vec::new(),
+            statuses: Vec::new(),
+            amount: (Bound::Unbounded, Bound::Unbounded),
+            time: (Bound::Unbounded, Bound::Unbounded),
+            description: None,
+        }
+    }
+}
+
+impl Query {
+    /// Фильтр без условий, пропускает все записи
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Пользователь - отправитель или получатель
+    pub fn user(mut self, user_id: u64) -> Self {
+        self.user = Some(user_id);
+        self
+    }
+
+    pub fn from_user(mut self, user_id: u64) -> Self {
+        self.from_user = Some(user_id);
+        self
+    }
+
+    pub fn to_user(mut self, user_id: u64) -> Self {
+        self.to_user = Some(user_id);
+        self
+    }
+
+    /// Добавляет тип к допустимым
+    pub fn tx_type(mut self, tx_type: TxType) -> Self {
+        self.types.push(tx_type);
+        self
+    }
+
+    /// Добавляет статус к допустимым
+    pub fn status(mut self, status: TxStatus) -> Self {
+        self.statuses.push(status);
+        self
+    }
+
+    pub fn amount(mut self, range: impl RangeBounds<i64>) -> Self {
+        self.amount = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Диапазон TIMESTAMP в миллисекундах
+    pub fn time(mut self, range: impl RangeBounds<u64>) -> Self {
+        self.time = (range.start_bound().cloned(), range.end_bound().cloned());
+        self
+    }
+
+    /// Регулярное выражение, которое должно найтись в описании
+    pub fn description(mut self, regex: Regex) -> Self {
+        self.description = Some(regex);
+        self
+    }
+
+    /// Добавляет условие, заданное текстом, см. [TERMS]:
+    ///
+    /// - `user`, `from-user`, `to-user` - USER_ID;
+    /// - `type`, `status` - список через запятую: `transfer,deposit`;
+    /// - `amount` - сумма или диапазон: `100..=500`, `..0`;
+    /// - `time` - момент или диапазон из миллисекунд или дат UTC `ГГГГ-ММ-ДД[THH:MM[:SS]]`.
+    ///   Дата без диапазона - весь период: `2021-03` - март, `2021-03-08` - день;
+    /// - `desc` - регулярное выражение.
+    pub fn term(self, term: &str, value: &str) -> Result<Self, QueryError> {
+        let invalid = |reason: String| QueryError::InvalidValue {
+            term: term.to_string(),
+            value: value.to_string(),
+            reason,
+        };
+        let query = match term {
+            "user" => self.user(terms::number(value).map_err(invalid)?),
+            "from-user" => self.from_user(terms::number(value).map_err(invalid)?),
+            "to-user" => self.to_user(terms::number(value).map_err(invalid)?),
+            "type" => {
+                let types = terms::list(value).map_err(invalid)?;
+                types.into_iter().fold(self, Query::tx_type)
+            }
+            "status" => {
+                let statuses = terms::list(value).map_err(invalid)?;
+                statuses.into_iter().fold(self, Query::status)
+            }
+            "amount" => {
+                let range = terms::range(value, terms::number::<i64>, |v| {
+                    let amount = terms::number(v)?;
+                    Ok((Bound::Included(amount), Bound::Included(amount)))
+                });
+                self.amount(range.map_err(invalid)?)
+            }
+            "time" => self.time(terms::time_range(value).map_err(invalid)?),
+            "desc" => self.description(Regex::new(value).map_err(|e| invalid(e.to_string()))?),
+            _ => return Err(QueryError::UnknownTerm(term.to_string())),
+        };
+        Ok(query)
+    }
+
+    pub fn matches(&self, record: &TxRecord) -> bool {
+        self.matches_ref(record.to_ref())
+    }
+
+    /// Проверка заимствованной записи, например из [BinMmap](crate::formats::mmap::BinMmap)
+    pub fn matches_ref(&self, record: TxRecordRef) -> bool {
+        let user = |id: Option<u64>, value: u64| id.is_none_or(|id| id == value);
+        user(self.from_user, record.from_user_id)
+            && user(self.to_user, record.to_user_id)
+            && self
+                .user
+                .is_none_or(|id| id == record.from_user_id || id == record.to_user_id)
+            && (self.types.is_empty() || self.types.contains(&record.tx_type))
+            && (self.statuses.is_empty() || self.statuses.contains(&record.status))
+            && self.amount.contains(&record.amount)
+            && self.time.contains(&record.timestamp)
+            && self
+                .description
+                .as_ref()
+                .is_none_or(|regex| regex.is_match(record.description))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RecordReader, formats::binary::BinReader};
+
+    const EXAMPLE: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    fn select(query: &Query) -> Vec<TxRecord> {
+        BinReader::new(EXAMPLE)
+            .records()
+            .map(Result::unwrap)
+            .filter(|record| query.matches(record))
+            .collect()
+    }
+
+    #[test]
+    fn test_query_builder() {
+        assert_eq!(select(&Query::new()).len(), 1000);
+
+        let pending_transfers = Query::new()
+            .tx_type(TxType::Transfer)
+            .status(TxStatus::Pending);
+        let selected = select(&pending_transfers);
+        assert_eq!(selected.len(), 333);
+        assert!(!selected.is_empty());
+        assert!(
+            selected
+                .iter()
+                .all(|r| r.tx_type == TxType::Transfer && r.status == TxStatus::Pending)
+        );
+
+        let user = selected[0].from_user_id;
+        let selected = select(&pending_transfers.clone().user(user));
+        assert!(
+            selected
+                .iter()
+                .all(|r| r.from_user_id == user || r.to_user_id == user)
+        );
+
+        let selected = select(
+            &Query::new()
+                .amount(..=1000)
+                .description(Regex::new(r"\b1\d\b").unwrap()),
+        );
+        assert!(!selected.is_empty());
+        assert!(selected.iter().all(|r| r.amount <= 1000));
+    }
+
+    #[test]
+    fn test_query_terms() {
+        let query = Query::new()
+            .term("type", "deposit,withdrawal")
+            .unwrap()
+            .term("time", "2021-10-01..2021-10-02")
+            .unwrap()
+            .term("amount", "100..")
+            .unwrap();
+        let selected = select(&query);
+        assert!(!selected.is_empty());
+        for record in &selected {
+            assert_ne!(record.tx_type, TxType::Transfer);
+            assert!((1633046400000..1633132800000).contains(&record.timestamp));
+            assert!(record.amount >= 100);
+        }
+        let all_day = select(&Query::new().term("time", "2021-10-01").unwrap());
+        assert!(all_day.len() >= selected.len());
+
+        assert_eq!(
+            Query::new().term("owner", "1").unwrap_err(),
+            QueryError::UnknownTerm("owner".into())
+        );
+        let err = Query::new().term("desc", "(").unwrap_err();
+        assert!(matches!(err, QueryError::InvalidValue { ref term, .. } if term == "desc"));
+        assert!(Query::new().term("status", "done").is_err());
+    }
+}
@@ -0,0 +1,278 @@
+//! Разбор текстовых значений условий: списки, диапазоны, даты
+
+use std::{ops::Bound, str::FromStr};
+
+/// Границы диапазона `(начало, конец)`
+pub type Range<T> = (Bound<T>, Bound<T>);
+
+/// Список через запятую, регистр не важен: `transfer,DEPOSIT`
+pub fn list<T: FromStr>(value: &str) -> Result<Vec<T>, String> {
+    value
+        .split(',')
+        .map(|item| {
+            let item = item.trim();
+            item.to_uppercase()
+                .parse()
+                .map_err(|_| format!("неизвестное значение {:?}", item))
+        })
+        .collect()
+}
+
+/// Диапазон `a..b`, `a..=b`, `a..`, `..b` или одно значение, `point` переводит его в диапазон
+pub fn range<T: Copy>(
+    value: &str,
+    parse: impl Fn(&str) -> Result<T, String>,
+    point: impl Fn(&str) -> Result<Range<T>, String>,
+) -> Result<Range<T>, String> {
+    let Some((start, end)) = value.split_once("..") else {
+        return point(value);
+    };
+    let start = match start.trim() {
+        "" => Bound::Unbounded,
+        start => Bound::Included(parse(start)?),
+    };
+    let end = match end.strip_prefix('=') {
+        Some(end) => Bound::Included(parse(end.trim())?),
+        None if end.trim().is_empty() => Bound::Unbounded,
+        None => Bound::Excluded(parse(end.trim())?),
+    };
+    Ok((start, end))
+}
+
+pub fn number<T: FromStr>(value: &str) -> Result<T, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("{:?} - не число", value))
+}
+
+/// Момент времени в миллисекундах: число или дата UTC `ГГГГ-ММ-ДД[THH:MM[:SS]]`
+pub fn timestamp(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        return number(value);
+    }
+    let (start, _) = period(value)?;
+    Ok(start)
+}
+
+/// Диапазон времени. Включённый конец-дата включает весь её период:
+/// `2021-01..=2021-03` - до конца марта
+pub fn time_range(value: &str) -> Result<Range<u64>, String> {
+    let (start, end) = range(value, timestamp, time_point)?;
+    match value.split_once("..=") {
+        Some((_, last)) => Ok((start, time_point(last)?.1)),
+        None => Ok((start, end)),
+    }
+}
+
+/// Одно значение времени как диапазон: число - ровно этот момент,
+/// `ГГГГ-ММ` - весь месяц, `ГГГГ-ММ-ДД` - весь день
+pub fn time_point(value: &str) -> Result<Range<u64>, String> {
+    let value = value.trim();
+    if value.bytes().all(|b| b.is_ascii_digit()) {
+        let ms = number(value)?;
+        return Ok((Bound::Included(ms), Bound::Included(ms)));
+    }
+    let (start, end) = period(value)?;
+    Ok((Bound::Included(start), Bound::Excluded(end)))
+}
+
+/// Период, заданный датой: начало и конец в миллисекундах
+fn period(value: &str) -> Result<(u64, u64), String> {
+    let invalid = || format!("{:?} - не дата ГГГГ-ММ-ДД[THH:MM[:SS]]", value);
+    let (date, time) = match value.split_once(['T', ' ']) {
+        Some((date, time)) => (date, Some(time)),
+        None => (value, None),
+    };
+
+    let parts: Vec<&str> = date.split('-').collect();
+    let field = |i: usize, max: u64| -> Result<Option<u64>, String> {
+        match parts.get(i) {
+            None => Ok(None),
+            Some(part) => match part.parse::<u64>() {
+                Ok(n) if (1..=max).contains(&n) => Ok(Some(n)),
+                _ => Err(invalid()),
+            },
+        }
+    };
+    if !(2..=3).contains(&parts.len()) {
+        return Err(invalid());
+    }
+    let year = parts[0]
+        .parse::<u64>()
+        .ok()
+        .filter(|y| (1970..=9999).contains(y))
+        .ok_or_else(invalid)?;
+    let month = field(1, 12)?.ok_or_else(invalid)?;
+    let Some(day) = field(2, days_in_month(year, month))? else {
+        let end = match month {
+            12 => days_from_civil(year + 1, 1, 1),
+            _ => days_from_civil(year, month + 1, 1),
+        };
+        return Ok((days_from_civil(year, month, 1) * DAY, end * DAY));
+    };
+
+    let start = days_from_civil(year, month, day) * DAY;
+    let Some(time) = time else {
+        return Ok((start, start + DAY));
+    };
+    let parts: Vec<u64> = time
+        .split(':')
+        .map(|p| p.parse().map_err(|_| invalid()))
+        .collect::<Result<_, _>>()?;
+    let (seconds, len) = match parts.as_slice() {
+        [h, m] if *h < 24 && *m < 60 => (h * 3600 + m * 60, 60),
+        [h, m, s] if *h < 24 && *m < 60 && *s < 60 => (h * 3600 + m * 60 + s, 1),
+        _ => return Err(invalid()),
+    };
+    let start = start + seconds * 1000;
+    Ok((start, start + len * 1000))
+}
+
+const DAY: u64 = 24 * 60 * 60 * 1000;
+
+/// Год и месяц UTC момента в миллисекундах
+///
+/// Без перебора лет, алгоритм `civil_from_days` Говарда Хиннанта: счёт идёт
+/// в 400-летних эрах от 0000-03-01, чтобы високосный день был последним в году
+pub fn year_month(ms: u64) -> (u64, u64) {
+    // Дней от 0000-03-01 до 1970-01-01
+    let days = ms / DAY + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Месяцы с марта: 0 - март, 11 - февраль
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    (year, month)
+}
+
+fn is_leap(year: u64) -> bool {
+    year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400))
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if is_leap(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Дней от 1970-01-01 до даты
+///
+/// Обратный к [year_month] алгоритм `days_from_civil`: год считается с марта
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = year - u64::from(month <= 2);
+    let era = year / 400;
+    let year_of_era = year % 400;
+    // Месяцы с марта: 0 - март, 11 - февраль
+    let shifted_month = (month + 9) % 12;
+    let day_of_year = (153 * shifted_month + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::TxType;
+
+    #[test]
+    fn test_terms_ranges() {
+        let amount = |v| {
+            range(v, number::<i64>, |v| {
+                number(v).map(|n| (Bound::Included(n), Bound::Included(n)))
+            })
+        };
+        assert_eq!(
+            amount("-100..=500"),
+            Ok((Bound::Included(-100), Bound::Included(500)))
+        );
+        assert_eq!(amount("..5"), Ok((Bound::Unbounded, Bound::Excluded(5))));
+        assert_eq!(amount("7.."), Ok((Bound::Included(7), Bound::Unbounded)));
+        assert_eq!(amount("3"), Ok((Bound::Included(3), Bound::Included(3))));
+        assert!(amount("x..5").is_err());
+
+        assert_eq!(
+            list("transfer, DEPOSIT"),
+            Ok(vec![TxType::Transfer, TxType::Deposit])
+        );
+        assert!(list::<TxType>("refund").is_err());
+    }
+
+    #[test]
+    fn test_terms_dates() {
+        // 2021-10-01T00:00:00Z
+        let october = 1633046400000;
+        assert_eq!(timestamp("2021-10-01"), Ok(october));
+        assert_eq!(timestamp("2021-10-01T00:01"), Ok(october + 60_000));
+        assert_eq!(timestamp("1633036860000"), Ok(1633036860000));
+        assert_eq!(
+            time_point("2021-10"),
+            Ok((
+                Bound::Included(october),
+                Bound::Excluded(october + 31 * DAY)
+            ))
+        );
+        assert_eq!(
+            time_point("2024-02-29"),
+            Ok((
+                Bound::Included(1709164800000),
+                Bound::Excluded(1709164800000 + DAY)
+            ))
+        );
+        assert_eq!(timestamp("2020-12-31T23:59:59"), Ok(1609459199000));
+        assert_eq!(
+            time_range("2021-01..=2021-03"),
+            Ok((
+                Bound::Included(1609459200000),
+                Bound::Excluded(1617235200000)
+            ))
+        );
+        assert_eq!(
+            time_range("..=2021-10-01T00:01"),
+            Ok((Bound::Unbounded, Bound::Excluded(october + 120_000)))
+        );
+        assert_eq!(
+            time_range("2021-01..2021-03"),
+            Ok((
+                Bound::Included(1609459200000),
+                Bound::Excluded(1614556800000)
+            ))
+        );
+        assert_eq!(
+            time_range("1000..=2000"),
+            Ok((Bound::Included(1000), Bound::Included(2000)))
+        );
+        for (year, month, day) in [(1970, 1, 1), (2000, 2, 29), (2100, 3, 1), (9999, 12, 31)] {
+            let ms = days_from_civil(year, month, day) * DAY;
+            assert_eq!(year_month(ms), (year, month));
+        }
+        assert_eq!(days_from_civil(2021, 10, 1) * DAY, october);
+        assert_eq!(year_month(0), (1970, 1));
+        assert_eq!(year_month(1609459199000), (2020, 12));
+        assert_eq!(year_month(1609459200000), (2021, 1));
+        assert_eq!(year_month(1709164800000), (2024, 2));
+        assert_eq!(year_month(253402300799000), (9999, 12));
+        assert_eq!(year_month(u64::MAX), (584556019, 4));
+        for invalid in [
+            "2021-13-01",
+            "2023-02-29",
+            "2021-10-1x",
+            "2021-10-01T25:00",
+            "1969-01-01",
+        ] {
+            assert!(timestamp(invalid).is_err(), "{}", invalid);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+mod status;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+mod types;
+
+pub use status::TxStatus;
+pub use types::TxType;
+
+/// Имена полей записи в порядке спецификации
+pub const FIELDS: [&str; 8] = [
+    "TX_ID",
+    "TX_TYPE",
+    "FROM_USER_ID",
+    "TO_USER_ID",
+    "AMOUNT",
+    "TIMESTAMP",
+    "STATUS",
+    "DESCRIPTION",
+];
+
+/// Запись о транзакции YPBank - общая модель для всех форматов
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TxRecord {
+    pub tx_id: u64,
+    pub tx_type: TxType,
+    pub from_user_id: u64,
+    pub to_user_id: u64,
+    /// Сумма в наименьших единицах валюты (центах)
+    pub amount: i64,
+    /// Unix-время в миллисекундах
+    pub timestamp: u64,
+    pub status: TxStatus,
+    pub description: String,
+}
+
+/// Запись, заимствующая описание из буфера разбора, см. [crate::formats::mmap]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TxRecordRef<'a> {
+    pub tx_id: u64,
+    pub tx_type: TxType,
+    pub from_user_id: u64,
+    pub to_user_id: u64,
+    pub amount: i64,
+    pub timestamp: u64,
+    pub status: TxStatus,
+    pub description: &'a str,
+}
+
+impl TxRecordRef<'_> {
+    /// Копирует запись вместе с описанием
+    pub fn into_owned(self) -> TxRecord {
+        TxRecord {
+            tx_id: self.tx_id,
+            tx_type: self.tx_type,
+            from_user_id: self.from_user_id,
+            to_user_id: self.to_user_id,
+            amount: self.amount,
+            timestamp: self.timestamp,
+            status: self.status,
+            description: self.description.to_string(),
+        }
+    }
+}
+
+impl From<TxRecordRef<'_>> for TxRecord {
+    fn from(record: TxRecordRef<'_>) -> Self {
+        record.into_owned()
+    }
+}
+
+impl TxRecord {
+    /// Заимствующее представление записи
+    pub fn to_ref(&self) -> TxRecordRef<'_> {
+        TxRecordRef {
+            tx_id: self.tx_id,
+            tx_type: self.tx_type,
+            from_user_id: self.from_user_id,
+            to_user_id: self.to_user_id,
+            amount: self.amount,
+            timestamp: self.timestamp,
+            status: self.status,
+            description: &self.description,
+        }
+    }
+}
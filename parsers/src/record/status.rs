@@ -0,0 +1,39 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Статус транзакции
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxStatus {
+    Success,
+    Failure,
+    Pending,
+}
+
+impl TxStatus {
+    /// Имя статуса так, как оно записывается в CSV и текстовом формате
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxStatus::Success => "SUCCESS",
+            TxStatus::Failure => "FAILURE",
+            TxStatus::Pending => "PENDING",
+        }
+    }
+}
+
+impl Display for TxStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TxStatus {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "SUCCESS" => Ok(TxStatus::Success),
+            "FAILURE" => Ok(TxStatus::Failure),
+            "PENDING" => Ok(TxStatus::Pending),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,33 @@
+//! Записи для тестов. Вне крейта доступны с возможностью `testing`
+
+use super::{TxRecord, TxStatus, TxType};
+use crate::formats::{RecordWriter, binary::BinWriter};
+
+/// Успешная операция без описания
+pub fn record(tx_id: u64, tx_type: TxType, from: u64, to: u64, amount: i64) -> TxRecord {
+    TxRecord {
+        tx_id,
+        tx_type,
+        from_user_id: from,
+        to_user_id: to,
+        amount,
+        timestamp: 1633036860000,
+        status: TxStatus::Success,
+        description: String::new(),
+    }
+}
+
+/// Списание с описанием `description`
+pub fn described(tx_id: u64, description: &str) -> TxRecord {
+    TxRecord {
+        description: description.into(),
+        ..record(tx_id, TxType::Withdrawal, 7, 0, -1500)
+    }
+}
+
+/// Записи в YPBankBin версии 1
+pub fn encode_bin(records: &[TxRecord]) -> Vec<u8> {
+    let mut writer = BinWriter::new(Vec::new());
+    writer.write_all(records).unwrap();
+    writer.into_inner().unwrap()
+}
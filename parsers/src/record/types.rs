@@ -0,0 +1,39 @@
+use std::{fmt::Display, str::FromStr};
+
+/// Тип транзакции
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TxType {
+    Deposit,
+    Transfer,
+    Withdrawal,
+}
+
+impl TxType {
+    /// Имя типа так, как оно записывается в CSV и текстовом формате
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TxType::Deposit => "DEPOSIT",
+            TxType::Transfer => "TRANSFER",
+            TxType::Withdrawal => "WITHDRAWAL",
+        }
+    }
+}
+
+impl Display for TxType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TxType {
+    type Err = ();
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "DEPOSIT" => Ok(TxType::Deposit),
+            "TRANSFER" => Ok(TxType::Transfer),
+            "WITHDRAWAL" => Ok(TxType::Withdrawal),
+            _ => Err(()),
+        }
+    }
+}
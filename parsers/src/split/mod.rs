@@ -0,0 +1,492 @@
+//! Разбиение файла YPBank на части по ключу
+//!
+//! Каждая запись попадает в файл своей части. Путь файла получается из шаблона
+//! подстановкой значения ключа вместо `{key}`: `teams/{key}.csv` даёт `teams/42.csv`.
+//! Внутри части записи идут в порядке входа.
+//!
+//! Частей может быть больше, чем разрешено открытых файлов, поэтому открытыми держатся
+//! не более [Splitter::max_open] писателей. Давно не использованный закрывается,
+//! а следующая запись в его часть дописывается в конец файла без повторного заголовка.
+
+use crate::{
+    compress::{self, CompressWriter, Compression},
+    errors::ParseError,
+    formats::{
+        Format, RecordReader, RecordWriter,
+        binary::{BinVersion, BinWriter},
+        csv::CsvWriter,
+    },
+    query::terms::year_month,
+    record::TxRecord,
+};
+use std::{
+    cell::RefCell,
+    collections::{BTreeMap, HashMap},
+    fmt::Display,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+    str::FromStr,
+};
+
+/// Место значения ключа в шаблоне пути
+pub const KEY_PLACEHOLDER: &str = "{key}";
+
+/// Сколько файлов держится открытыми по умолчанию
+pub const DEFAULT_MAX_OPEN: usize = 128;
+
+/// Ключ разбиения
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SplitKey {
+    /// Оба участника: запись попадает в часть отправителя и в часть получателя.
+    /// Внешний счёт 0 (источник DEPOSIT, получатель WITHDRAWAL) своей части не получает
+    User,
+    FromUser,
+    ToUser,
+    /// Месяц TIMESTAMP по UTC, `YYYY-MM`
+    Month,
+    /// TX_TYPE в нижнем регистре
+    TxType,
+    /// STATUS в нижнем регистре
+    Status,
+}
+
+impl SplitKey {
+    pub const ALL: [SplitKey; 6] = [
+        SplitKey::User,
+        SplitKey::FromUser,
+        SplitKey::ToUser,
+        SplitKey::Month,
+        SplitKey::TxType,
+        SplitKey::Status,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SplitKey::User => "user",
+            SplitKey::FromUser => "from-user",
+            SplitKey::ToUser => "to-user",
+            SplitKey::Month => "month",
+            SplitKey::TxType => "type",
+            SplitKey::Status => "status",
+        }
+    }
+
+    /// Значения ключа записи - части, в которые она попадает
+    pub fn values(&self, record: &TxRecord) -> Vec<String> {
+        match self {
+            SplitKey::User => {
+                let (from, to) = (record.from_user_id, record.to_user_id);
+                match (from, to) {
+                    (0, 0) => vec!["0".to_string()],
+                    (0, user) | (user, 0) => vec![user.to_string()],
+                    _ if from == to => vec![from.to_string()],
+                    _ => vec![from.to_string(), to.to_string()],
+                }
+            }
+            SplitKey::FromUser => vec![record.from_user_id.to_string()],
+            SplitKey::ToUser => vec![record.to_user_id.to_string()],
+            SplitKey::Month => {
+                let (year, month) = year_month(record.timestamp);
+                vec![format!("{:04}-{:02}", year, month)]
+            }
+            SplitKey::TxType => vec![record.tx_type.to_string().to_lowercase()],
+            SplitKey::Status => vec![record.status.to_string().to_lowercase()],
+        }
+    }
+}
+
+impl Display for SplitKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for SplitKey {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        SplitKey::ALL
+            .into_iter()
+            .find(|key| key.as_str() == value)
+            .ok_or_else(|| format!("неизвестный ключ разбиения: {}", value))
+    }
+}
+
+/// Ошибки разбиения
+#[derive(Debug)]
+pub enum SplitError {
+    /// Шаблон пути без `{key}` или без расширения формата
+    Template(String),
+
+    /// Ошибка чтения входа
+    Input(ParseError),
+
+    /// Ошибка записи части
+    Output {
+        path: PathBuf,
+        error: Box<ParseError>,
+    },
+}
+
+impl Display for SplitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SplitError::Template(message) => write!(f, "шаблон пути: {}", message),
+            SplitError::Input(err) => write!(f, "{}", err),
+            SplitError::Output { path, error } => write!(f, "{}: {}", path.display(), error),
+        }
+    }
+}
+
+impl std::error::Error for SplitError {}
+
+/// Записанная часть
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPart {
+    pub key: String,
+    pub path: PathBuf,
+    pub records: u64,
+}
+
+/// Итог разбиения
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SplitReport {
+    /// Прочитано записей входа
+    pub read: u64,
+    /// Части в порядке первой записи
+    pub parts: Vec<SplitPart>,
+    /// Сколько раз закрытый файл открывался снова на дописывание
+    pub reopened: u64,
+}
+
+/// Разбиение на части по ключу
+///
+/// ```no_run
+/// use parsers::{Format, compress, split::{SplitKey, Splitter}};
+///
+/// let mut input = Format::Bin.reader(compress::open("records.bin")?);
+/// let report = Splitter::new(SplitKey::User, "teams/{key}.csv.gz").split(&mut input)?;
+/// println!("частей: {}", report.parts.len());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct Splitter {
+    key: SplitKey,
+    template: String,
+    format: Option<Format>,
+    bin_version: BinVersion,
+    max_open: usize,
+}
+
+impl Splitter {
+    /// `template` - путь файла части с `{key}`
+    pub fn new(key: SplitKey, template: impl Into<String>) -> Self {
+        Self {
+            key,
+            template: template.into(),
+            format: None,
+            bin_version: BinVersion::default(),
+            max_open: DEFAULT_MAX_OPEN,
+        }
+    }
+
+    /// Формат частей. Без него определяется по расширению шаблона
+    pub fn format(mut self, format: Format) -> Self {
+        self.format = Some(format);
+        self
+    }
+
+    /// Версия YPBankBin для частей в формате `bin`
+    pub fn bin_version(mut self, version: BinVersion) -> Self {
+        self.bin_version = version;
+        self
+    }
+
+    /// Сколько файлов частей держать открытыми, не меньше 1
+    pub fn max_open(mut self, files: usize) -> Self {
+        self.max_open = files.max(1);
+        self
+    }
+
+    /// Путь файла части со значением ключа `value`
+    pub fn path(&self, value: &str) -> PathBuf {
+        PathBuf::from(self.template.replace(KEY_PLACEHOLDER, value))
+    }
+
+    /// Раскладывает записи `input` по файлам частей. Существующие файлы перезаписываются.
+    ///
+    /// Ошибки пропущенных в нестрогом режиме записей остаются во входе, см. [RecordReader::take_errors].
+    pub fn split<R: RecordReader + ?Sized>(
+        &self,
+        input: &mut R,
+    ) -> Result<SplitReport, SplitError> {
+        if !self.template.contains(KEY_PLACEHOLDER) {
+            return Err(SplitError::Template(format!(
+                "нет подстановки {}",
+                KEY_PLACEHOLDER
+            )));
+        }
+        let format = self
+            .format
+            .or_else(|| Format::from_path(&self.template))
+            .ok_or_else(|| {
+                SplitError::Template("формат не определяется по расширению".to_string())
+            })?;
+
+        let mut parts = Parts {
+            splitter: self,
+            format,
+            report: SplitReport::default(),
+            writers: Vec::new(),
+            index: HashMap::new(),
+            open: BTreeMap::new(),
+            tick: 0,
+        };
+        while let Some(record) = input.read_record().map_err(SplitError::Input)? {
+            parts.report.read += 1;
+            for value in self.key.values(&record) {
+                parts.write(value, &record)?;
+            }
+        }
+        parts.close_all()?;
+        Ok(parts.report)
+    }
+}
+
+/// Писатель части и момент последней записи в него
+struct OpenPart {
+    writer: Box<dyn RecordWriter>,
+    /// Файл под писателем: при закрытии в нём завершается сжатый кадр
+    file: PartFile,
+    used: u64,
+}
+
+/// Файл части, общий для писателя формата и [OpenPart]
+#[derive(Clone)]
+struct PartFile(Rc<RefCell<CompressWriter<BufWriter<File>>>>);
+
+impl Write for PartFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.borrow_mut().flush()
+    }
+}
+
+/// Состояние разбиения: части, их писатели и очередь открытых файлов
+struct Parts<'a> {
+    splitter: &'a Splitter,
+    format: Format,
+    report: SplitReport,
+    /// Писатели в порядке [SplitReport::parts], `None` - файл закрыт
+    writers: Vec<Option<OpenPart>>,
+    /// Номер части по значению ключа
+    index: HashMap<String, usize>,
+    /// Открытые части по моменту последней записи, первой закрывается самая старая
+    open: BTreeMap<u64, usize>,
+    tick: u64,
+}
+
+impl Parts<'_> {
+    fn write(&mut self, value: String, record: &TxRecord) -> Result<(), SplitError> {
+        let part = match self.index.get(&value) {
+            Some(&part) => part,
+            None => {
+                let part = self.report.parts.len();
+                self.report.parts.push(SplitPart {
+                    path: self.splitter.path(&value),
+                    key: value.clone(),
+                    records: 0,
+                });
+                self.writers.push(None);
+                self.index.insert(value, part);
+                part
+            }
+        };
+
+        match self.writers[part].as_ref().map(|open| open.used) {
+            Some(used) => {
+                self.open.remove(&used);
+            }
+            None => {
+                if self.open.len() >= self.splitter.max_open
+                    && let Some((_, oldest)) = self.open.pop_first()
+                {
+                    close(&mut self.writers[oldest], &self.report.parts[oldest].path)?;
+                }
+                let SplitPart { path, records, .. } = &self.report.parts[part];
+                let append = *records > 0;
+                let open = open_part(path, self.format, self.splitter.bin_version, append)
+                    .map_err(|error| output_error(path, error))?;
+                self.writers[part] = Some(open);
+                if append {
+                    self.report.reopened += 1;
+                }
+            }
+        }
+
+        let open = self.writers[part].as_mut().expect("часть открыта");
+        open.used = self.tick;
+        self.open.insert(self.tick, part);
+        self.tick += 1;
+        let SplitPart { path, records, .. } = &mut self.report.parts[part];
+        open.writer
+            .write_record(record)
+            .map_err(|error| output_error(path, error))?;
+        *records += 1;
+        Ok(())
+    }
+
+    fn close_all(&mut self) -> Result<(), SplitError> {
+        for (writer, part) in self.writers.iter_mut().zip(&self.report.parts) {
+            close(writer, &part.path)?;
+        }
+        self.open.clear();
+        Ok(())
+    }
+}
+
+/// Завершает писатель части, если он открыт
+fn close(writer: &mut Option<OpenPart>, path: &Path) -> Result<(), SplitError> {
+    if let Some(OpenPart {
+        mut writer, file, ..
+    }) = writer.take()
+    {
+        writer.finish().map_err(|error| output_error(path, error))?;
+        drop(writer);
+        file.0
+            .borrow_mut()
+            .finish()
+            .map_err(|error| output_error(path, error.into()))?;
+    }
+    Ok(())
+}
+
+fn output_error(path: &Path, error: ParseError) -> SplitError {
+    SplitError::Output {
+        path: path.to_path_buf(),
+        error: Box::new(error),
+    }
+}
+
+/// Открывает файл части. Новый файл создаётся вместе с каталогами, при `append`
+/// запись продолжается с конца файла, а заголовок не повторяется
+fn open_part(
+    path: &Path,
+    format: Format,
+    version: BinVersion,
+    append: bool,
+) -> Result<OpenPart, ParseError> {
+    let output = if append {
+        let file = OpenOptions::new().append(true).open(path)?;
+        CompressWriter::new(BufWriter::new(file), Compression::from_path(path))
+    } else {
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        compress::create(path)?
+    };
+    let file = PartFile(Rc::new(RefCell::new(output)));
+    let output = file.clone();
+    let writer: Box<dyn RecordWriter> = match format {
+        Format::Csv if append => Box::new(CsvWriter::new(output).appending()),
+        Format::Bin if append => Box::new(BinWriter::with_version(output, version).appending()),
+        Format::Bin => Box::new(BinWriter::with_version(output, version)),
+        _ => format.writer(output),
+    };
+    Ok(OpenPart {
+        writer,
+        file,
+        used: 0,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        TxStatus, TxType,
+        formats::{binary::BinReader, csv::CsvReader},
+    };
+    use assert_matches::assert_matches;
+
+    const EXAMPLE_BIN: &[u8] = include_bytes!("../../../data/records_example.bin");
+
+    fn record(from_user_id: u64, to_user_id: u64, timestamp: u64) -> TxRecord {
+        TxRecord {
+            tx_id: 1,
+            tx_type: TxType::Transfer,
+            from_user_id,
+            to_user_id,
+            amount: 100,
+            timestamp,
+            status: TxStatus::Pending,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_split_key_values() {
+        let values = |key: SplitKey, record: &TxRecord| key.values(record);
+        let transfer = record(7, 9, 1633036860000);
+        assert_eq!(values(SplitKey::User, &transfer), vec!["7", "9"]);
+        assert_eq!(values(SplitKey::User, &record(0, 9, 0)), vec!["9"]);
+        assert_eq!(values(SplitKey::User, &record(7, 0, 0)), vec!["7"]);
+        assert_eq!(values(SplitKey::User, &record(7, 7, 0)), vec!["7"]);
+        assert_eq!(values(SplitKey::FromUser, &record(0, 9, 0)), vec!["0"]);
+        assert_eq!(values(SplitKey::ToUser, &transfer), vec!["9"]);
+        assert_eq!(values(SplitKey::Month, &transfer), vec!["2021-09"]);
+        assert_eq!(values(SplitKey::TxType, &transfer), vec!["transfer"]);
+        assert_eq!(values(SplitKey::Status, &transfer), vec!["pending"]);
+
+        for key in SplitKey::ALL {
+            assert_eq!(key.to_string().parse(), Ok(key));
+        }
+        assert!("day".parse::<SplitKey>().is_err());
+    }
+
+    #[test]
+    fn test_split_reopens_parts() {
+        let records = BinReader::new(EXAMPLE_BIN).read_all().unwrap();
+        let dir = std::env::temp_dir().join(format!("ypbank-split-test-{}", std::process::id()));
+        let template = dir.join("{key}/records.csv.gz");
+        // Один открытый файл: статусы чередуются, каждая часть много раз открывается снова
+        let report = Splitter::new(SplitKey::Status, template.to_str().unwrap())
+            .max_open(1)
+            .split(&mut BinReader::new(EXAMPLE_BIN))
+            .unwrap();
+
+        assert_eq!(report.read, 1000);
+        let keys: Vec<_> = report.parts.iter().map(|p| p.key.as_str()).collect();
+        assert_eq!(keys, vec!["failure", "pending", "success"]);
+        assert!(report.reopened > 900);
+        for part in &report.parts {
+            assert_eq!(part.path, dir.join(&part.key).join("records.csv.gz"));
+            let parsed = CsvReader::new(compress::open(&part.path).unwrap())
+                .read_all()
+                .unwrap();
+            let expected: Vec<_> = records
+                .iter()
+                .filter(|r| r.status.to_string().to_lowercase() == part.key)
+                .cloned()
+                .collect();
+            assert_eq!(parsed.len() as u64, part.records);
+            assert_eq!(parsed, expected);
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_split_template_errors() {
+        let split = |splitter: Splitter| splitter.split(&mut BinReader::new(EXAMPLE_BIN));
+        let err = split(Splitter::new(SplitKey::Month, "out.csv")).unwrap_err();
+        assert_matches!(err, SplitError::Template(_));
+        assert_eq!(err.to_string(), "шаблон пути: нет подстановки {key}");
+        let err = split(Splitter::new(SplitKey::Month, "out-{key}")).unwrap_err();
+        assert_matches!(err, SplitError::Template(_));
+    }
+}
@@ -0,0 +1,273 @@
+//! Семантическая проверка записей: согласованность полей, которую не проверяет парсер
+
+mod rules;
+
+pub use rules::{AmountSign, DepositFromZero, TimestampRange, UniqueTxId, WithdrawalToZero};
+
+use crate::{errors::ParseError, formats::Format, formats::RecordReader, record::TxRecord};
+use std::fmt::Display;
+
+/// Вид нарушения правила
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// У DEPOSIT ненулевой FROM_USER_ID
+    DepositFromUser { from_user_id: u64 },
+
+    /// У WITHDRAWAL ненулевой TO_USER_ID
+    WithdrawalToUser { to_user_id: u64 },
+
+    /// Знак AMOUNT не совпадает с направлением: зачисление положительное, списание отрицательное
+    AmountSign { amount: i64, credit: bool },
+
+    /// TX_ID уже встречался в записи `first_record`
+    DuplicateTxId { first_record: u64 },
+
+    /// TIMESTAMP вне допустимого диапазона, в миллисекундах
+    TimestampOutOfRange { timestamp: u64, min: u64, max: u64 },
+
+    /// Нарушение пользовательского правила
+    Custom { rule: &'static str, message: String },
+}
+
+impl Display for ViolationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ViolationKind::DepositFromUser { from_user_id } => {
+                write!(f, "DEPOSIT с FROM_USER_ID {}, ожидается 0", from_user_id)
+            }
+            ViolationKind::WithdrawalToUser { to_user_id } => {
+                write!(f, "WITHDRAWAL с TO_USER_ID {}, ожидается 0", to_user_id)
+            }
+            ViolationKind::AmountSign { amount, credit } => {
+                let expected = if *credit {
+                    "положительная для зачисления"
+                } else {
+                    "отрицательная для списания"
+                };
+                write!(f, "AMOUNT {}, ожидается {}", amount, expected)
+            }
+            ViolationKind::DuplicateTxId { first_record } => {
+                write!(f, "TX_ID уже был в записи {}", first_record)
+            }
+            ViolationKind::TimestampOutOfRange {
+                timestamp,
+                min,
+                max,
+            } => {
+                write!(f, "TIMESTAMP {} вне диапазона {}..={}", timestamp, min, max)
+            }
+            ViolationKind::Custom { message, .. } => write!(f, "{}", message),
+        }
+    }
+}
+
+/// Нарушение правила в записи
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    /// Номер записи во входе, с 1
+    pub record: u64,
+    pub tx_id: u64,
+    /// Имя нарушенного правила, см. [Rule::name]
+    pub rule: &'static str,
+    pub kind: ViolationKind,
+}
+
+impl Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "запись {} (TX_ID {}): [{}] {}",
+            self.record, self.tx_id, self.rule, self.kind
+        )
+    }
+}
+
+/// Правило проверки записей
+pub trait Rule {
+    /// Имя правила, по нему правило отключается
+    fn name(&self) -> &'static str;
+
+    /// Проверяет очередную запись. Правило может хранить состояние между записями
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind>;
+}
+
+/// Результат проверки потока записей
+#[derive(Debug, Default)]
+pub struct ValidationReport {
+    /// Количество проверенных записей
+    pub checked: u64,
+    pub violations: Vec<Violation>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Набор правил, применяемых к каждой записи
+#[derive(Default)]
+pub struct Validator {
+    rules: Vec<Box<dyn Rule>>,
+    checked: u64,
+}
+
+impl Validator {
+    /// Валидатор без правил
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Стандартные правила для файлов формата `format`.
+    /// Знак AMOUNT проверяется только в `bin`: в `csv`, `text` и `json` сумма неотрицательна
+    pub fn standard(format: Format) -> Self {
+        let validator = Self::new()
+            .with_rule(DepositFromZero)
+            .with_rule(WithdrawalToZero)
+            .with_rule(UniqueTxId::default())
+            .with_rule(TimestampRange::default());
+        match format {
+            Format::Bin => validator.with_rule(AmountSign),
+            Format::Csv | Format::Text | Format::Json => validator,
+        }
+    }
+
+    /// Добавляет правило
+    pub fn with_rule(mut self, rule: impl Rule + 'static) -> Self {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// Убирает правила с именами из `names`
+    pub fn without(mut self, names: &[&str]) -> Self {
+        self.rules.retain(|rule| !names.contains(&rule.name()));
+        self
+    }
+
+    /// Имена правил
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|rule| rule.name()).collect()
+    }
+
+    /// Проверяет очередную запись всеми правилами
+    pub fn check(&mut self, record: &TxRecord) -> Vec<Violation> {
+        self.checked += 1;
+        let number = self.checked;
+        self.rules
+            .iter_mut()
+            .filter_map(|rule| {
+                rule.check(record).map(|kind| Violation {
+                    record: number,
+                    tx_id: record.tx_id,
+                    rule: rule.name(),
+                    kind,
+                })
+            })
+            .collect()
+    }
+
+    /// Проверяет все записи читателя потоком
+    pub fn validate<R: RecordReader + ?Sized>(
+        &mut self,
+        reader: &mut R,
+    ) -> Result<ValidationReport, ParseError> {
+        let start = self.checked;
+        let mut violations = Vec::new();
+        while let Some(record) = reader.read_record()? {
+            violations.extend(self.check(&record));
+        }
+        Ok(ValidationReport {
+            checked: self.checked - start,
+            violations,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        formats::{Format, csv::CsvReader},
+        record::{TxType, testing::record},
+    };
+    use assert_matches::assert_matches;
+
+    #[test]
+    fn test_validate_example_csv() {
+        let data = include_bytes!("../../../data/records_example.csv");
+        let mut reader = CsvReader::new(&data[..]);
+        let report = Validator::standard(Format::Csv)
+            .validate(&mut reader)
+            .unwrap();
+        assert_eq!(report.checked, 1000);
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn test_validate_rules() {
+        let records = vec![
+            record(1, TxType::Deposit, 5, 6, 100),
+            record(2, TxType::Withdrawal, 5, 6, -100),
+            record(1, TxType::Transfer, 5, 6, 100),
+            record(3, TxType::Withdrawal, 5, 0, 100),
+        ];
+        let mut validator = Validator::standard(Format::Bin);
+        let report = validator.validate(&mut records.into_iter()).unwrap();
+
+        let rules: Vec<(u64, &str)> = report
+            .violations
+            .iter()
+            .map(|v| (v.record, v.rule))
+            .collect();
+        assert_eq!(
+            rules,
+            vec![
+                (1, "deposit-from"),
+                (2, "withdrawal-to"),
+                (3, "unique-id"),
+                (4, "amount-sign")
+            ]
+        );
+        assert_matches!(
+            report.violations[2].kind,
+            ViolationKind::DuplicateTxId { first_record: 1 }
+        );
+        assert_eq!(
+            report.violations[3].to_string(),
+            "запись 4 (TX_ID 3): [amount-sign] AMOUNT 100, ожидается отрицательная для списания"
+        );
+    }
+
+    struct NoEmptyDescription;
+
+    impl Rule for NoEmptyDescription {
+        fn name(&self) -> &'static str {
+            "description"
+        }
+
+        fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+            record
+                .description
+                .is_empty()
+                .then(|| ViolationKind::Custom {
+                    rule: self.name(),
+                    message: "пустое описание".to_string(),
+                })
+        }
+    }
+
+    #[test]
+    fn test_validate_custom_rule_and_without() {
+        let mut validator = Validator::standard(Format::Bin)
+            .without(&["amount-sign", "timestamp"])
+            .with_rule(NoEmptyDescription);
+        assert_eq!(
+            validator.rule_names(),
+            vec!["deposit-from", "withdrawal-to", "unique-id", "description"]
+        );
+
+        let violations = validator.check(&record(1, TxType::Withdrawal, 5, 0, 100));
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "description");
+    }
+}
@@ -0,0 +1,159 @@
+use super::{Rule, ViolationKind};
+use crate::record::{TxRecord, TxType};
+use std::{
+    collections::HashMap,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// DEPOSIT приходит извне: FROM_USER_ID равен 0
+pub struct DepositFromZero;
+
+impl Rule for DepositFromZero {
+    fn name(&self) -> &'static str {
+        "deposit-from"
+    }
+
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+        (record.tx_type == TxType::Deposit && record.from_user_id != 0).then_some(
+            ViolationKind::DepositFromUser {
+                from_user_id: record.from_user_id,
+            },
+        )
+    }
+}
+
+/// WITHDRAWAL уходит наружу: TO_USER_ID равен 0
+pub struct WithdrawalToZero;
+
+impl Rule for WithdrawalToZero {
+    fn name(&self) -> &'static str {
+        "withdrawal-to"
+    }
+
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+        (record.tx_type == TxType::Withdrawal && record.to_user_id != 0).then_some(
+            ViolationKind::WithdrawalToUser {
+                to_user_id: record.to_user_id,
+            },
+        )
+    }
+}
+
+/// Знак AMOUNT в формате `bin`: DEPOSIT положительный, WITHDRAWAL отрицательный.
+/// Направление TRANSFER зависит от счёта, с которого на него смотрят, и не проверяется
+pub struct AmountSign;
+
+impl Rule for AmountSign {
+    fn name(&self) -> &'static str {
+        "amount-sign"
+    }
+
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+        let credit = match record.tx_type {
+            TxType::Deposit => true,
+            TxType::Withdrawal => false,
+            TxType::Transfer => return None,
+        };
+        let valid = if credit {
+            record.amount > 0
+        } else {
+            record.amount < 0
+        };
+        (!valid).then_some(ViolationKind::AmountSign {
+            amount: record.amount,
+            credit,
+        })
+    }
+}
+
+/// TX_ID не повторяются. Хранит номер первой записи для каждого TX_ID
+#[derive(Default)]
+pub struct UniqueTxId {
+    seen: HashMap<u64, u64>,
+    count: u64,
+}
+
+impl Rule for UniqueTxId {
+    fn name(&self) -> &'static str {
+        "unique-id"
+    }
+
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+        self.count += 1;
+        let first = *self.seen.entry(record.tx_id).or_insert(self.count);
+        (first != self.count).then_some(ViolationKind::DuplicateTxId {
+            first_record: first,
+        })
+    }
+}
+
+/// TIMESTAMP в миллисекундах попадает в диапазон `min..=max`
+pub struct TimestampRange {
+    pub min: u64,
+    pub max: u64,
+}
+
+impl TimestampRange {
+    /// 2000-01-01 в миллисекундах. Меньшие значения обычно оказываются секундами
+    pub const MIN: u64 = 946_684_800_000;
+
+    /// Допустимое опережение текущего времени
+    pub const FUTURE: Duration = Duration::from_secs(24 * 60 * 60);
+}
+
+/// С 2000 года по текущее время плюс [TimestampRange::FUTURE]
+impl Default for TimestampRange {
+    fn default() -> Self {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        Self {
+            min: Self::MIN,
+            max: (now + Self::FUTURE).as_millis() as u64,
+        }
+    }
+}
+
+impl Rule for TimestampRange {
+    fn name(&self) -> &'static str {
+        "timestamp"
+    }
+
+    fn check(&mut self, record: &TxRecord) -> Option<ViolationKind> {
+        (!(self.min..=self.max).contains(&record.timestamp)).then_some(
+            ViolationKind::TimestampOutOfRange {
+                timestamp: record.timestamp,
+                min: self.min,
+                max: self.max,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::record::TxStatus;
+
+    #[test]
+    fn test_timestamp_range() {
+        let mut record = TxRecord {
+            tx_id: 1,
+            tx_type: TxType::Transfer,
+            from_user_id: 1,
+            to_user_id: 2,
+            amount: 10,
+            timestamp: 1633036860,
+            status: TxStatus::Pending,
+            description: String::new(),
+        };
+        let mut rule = TimestampRange::default();
+        assert!(rule.check(&record).is_some());
+
+        record.timestamp = 1633036860000;
+        assert!(rule.check(&record).is_none());
+
+        record.timestamp = u64::MAX;
+        assert!(rule.check(&record).is_some());
+    }
+}
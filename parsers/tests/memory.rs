@@ -0,0 +1,180 @@
+//! Проверка, что читатели работают в постоянной памяти на больших файлах.
+//!
+//! Файл не создаётся на диске: вход генерируется на лету через `Read`.
+
+use parsers::{Format, RecordReader, RecordWriter, TxRecord, TxStatus, TxType};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::{Cell, RefCell},
+    io::{self, BufReader, Read, Write},
+    rc::Rc,
+};
+
+/// Аллокатор, считающий живые байты и их пик в текущем потоке
+struct CountingAlloc;
+
+thread_local! {
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn track(delta: isize) {
+    let _ = LIVE.try_with(|live| {
+        let value = live.get() + delta;
+        live.set(value);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(value)));
+    });
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        track(layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        track(-(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        track(new_size as isize - layout.size() as isize);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static ALLOC: CountingAlloc = CountingAlloc;
+
+/// Буфер, общий для генератора и писателя
+#[derive(Clone, Default)]
+struct Shared(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Генератор файла из `count` записей: байты создаются по мере чтения
+struct Synthetic {
+    writer: Box<dyn RecordWriter>,
+    out: Shared,
+    pos: usize,
+    next: u64,
+    count: u64,
+    /// Сколько байт отдано читателю
+    produced: u64,
+}
+
+impl Synthetic {
+    fn new(format: Format, count: u64) -> Self {
+        let out = Shared::default();
+        Self {
+            writer: format.writer(out.clone()),
+            out,
+            pos: 0,
+            next: 0,
+            count,
+            produced: 0,
+        }
+    }
+
+    fn record(i: u64) -> TxRecord {
+        TxRecord {
+            tx_id: 1_000_000 + i,
+            tx_type: TxType::Transfer,
+            from_user_id: i % 97,
+            to_user_id: i % 89,
+            amount: (i % 1000) as i64 + 1,
+            timestamp: 1633036860000 + i * 1000,
+            status: TxStatus::Success,
+            description: format!("Synthetic record {}", i),
+        }
+    }
+}
+
+impl Read for Synthetic {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.0.borrow().len() {
+            self.out.0.borrow_mut().clear();
+            self.pos = 0;
+            if self.next == self.count {
+                return Ok(0);
+            }
+            let record = Self::record(self.next);
+            self.writer
+                .write_record(&record)
+                .map_err(io::Error::other)?;
+            self.next += 1;
+        }
+
+        let out = self.out.0.borrow();
+        let len = buf.len().min(out.len() - self.pos);
+        buf[..len].copy_from_slice(&out[self.pos..self.pos + len]);
+        self.pos += len;
+        self.produced += len as u64;
+        Ok(len)
+    }
+}
+
+/// Прогоняет `count` записей через читатель, возвращает пик памяти и размер входа
+fn peak_memory(format: Format, count: u64) -> (isize, u64) {
+    let mut input = BufReader::new(Synthetic::new(format, count));
+    let base = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(base));
+
+    let mut processed = 0;
+    let mut checksum = 0u64;
+    for record in format.reader(&mut input).records() {
+        let record = record.unwrap();
+        checksum = checksum.wrapping_add(record.amount as u64);
+        processed += 1;
+    }
+    assert_eq!(processed, count);
+    assert!(checksum > 0);
+
+    let peak = PEAK.with(Cell::get) - base;
+    (peak, input.into_inner().produced)
+}
+
+#[test]
+fn test_readers_constant_memory() {
+    for format in Format::ALL {
+        let (small_peak, _) = peak_memory(format, 10_000);
+        let (large_peak, size) = peak_memory(format, 100_000);
+
+        assert!(size > 5 << 20, "{}: вход всего {} байт", format, size);
+        assert!(
+            large_peak < 1 << 20,
+            "{}: пик памяти {} байт на {} байт входа",
+            format,
+            large_peak,
+            size
+        );
+        // Память не растёт вместе с размером файла
+        assert!(
+            large_peak <= small_peak + 4096,
+            "{}: пик вырос с {} до {} байт",
+            format,
+            small_peak,
+            large_peak
+        );
+    }
+}
+
+/// Многогигабайтный прогон: `cargo test --release -p parsers --test memory -- --ignored`
+#[test]
+#[ignore]
+fn test_readers_constant_memory_huge() {
+    for format in Format::ALL {
+        let (peak, size) = peak_memory(format, 40_000_000);
+        assert!(size > 2 << 30);
+        assert!(peak < 1 << 20, "{}: пик памяти {} байт", format, peak);
+    }
+}
@@ -0,0 +1,197 @@
+//! Свойства: запись и чтение в любом формате возвращают те же записи,
+//! а читатели не паникуют на произвольном входе.
+
+use parsers::{
+    Format, ParseErrorKind, ParseMode, RecordReader, RecordWriter, TxRecord, TxStatus, TxType,
+    formats::{
+        binary::{BinReader, BinVersion, BinWriter},
+        parallel::ParTextReader,
+    },
+};
+use proptest::prelude::*;
+
+fn tx_type() -> impl Strategy<Value = TxType> {
+    prop_oneof![
+        Just(TxType::Deposit),
+        Just(TxType::Transfer),
+        Just(TxType::Withdrawal),
+    ]
+}
+
+fn tx_status() -> impl Strategy<Value = TxStatus> {
+    prop_oneof![
+        Just(TxStatus::Success),
+        Just(TxStatus::Failure),
+        Just(TxStatus::Pending),
+    ]
+}
+
+/// Запись с описанием из `description`
+fn tx_record(description: &'static str) -> impl Strategy<Value = TxRecord> {
+    (
+        any::<u64>(),
+        tx_type(),
+        any::<u64>(),
+        any::<u64>(),
+        any::<i64>(),
+        any::<u64>(),
+        tx_status(),
+        description,
+    )
+        .prop_map(
+            |(tx_id, tx_type, from_user_id, to_user_id, amount, timestamp, status, description)| {
+                TxRecord {
+                    tx_id,
+                    tx_type,
+                    from_user_id,
+                    to_user_id,
+                    amount,
+                    timestamp,
+                    status,
+                    description,
+                }
+            },
+        )
+}
+
+/// Текстовые форматы построчные: в описании нет перевода строки
+const LINE_DESCRIPTION: &str = "[^\n\r]{0,40}";
+/// В `bin` описание - любая строка UTF-8
+const ANY_DESCRIPTION: &str = "(?s).{0,40}";
+/// Описание с переводом строки, которое построчные форматы не записывают
+const BROKEN_LINE_DESCRIPTION: &str = "[^\n\r]{0,20}[\n\r][^\n\r]{0,20}";
+
+fn round_trip(format: Format, records: &[TxRecord]) -> Vec<TxRecord> {
+    let mut data = Vec::new();
+    let mut writer = format.writer(&mut data);
+    writer.write_all(records).unwrap();
+    writer.finish().unwrap();
+    drop(writer);
+
+    format.reader(data.as_slice()).read_all().unwrap()
+}
+
+proptest! {
+    #[test]
+    fn prop_csv_round_trip(records in prop::collection::vec(tx_record(LINE_DESCRIPTION), 0..8)) {
+        prop_assert_eq!(round_trip(Format::Csv, &records), records);
+    }
+
+    #[test]
+    fn prop_text_round_trip(records in prop::collection::vec(tx_record(LINE_DESCRIPTION), 0..8)) {
+        prop_assert_eq!(round_trip(Format::Text, &records), records);
+    }
+
+    #[test]
+    fn prop_line_writers_reject_line_breaks(record in tx_record(BROKEN_LINE_DESCRIPTION)) {
+        for format in [Format::Csv, Format::Text] {
+            let mut data = Vec::new();
+            let mut writer = format.writer(&mut data);
+            let err = writer.write_record(&record).unwrap_err();
+            prop_assert!(matches!(err.kind, ParseErrorKind::InvalidValue(_)), "{}", err);
+            prop_assert_eq!(err.field, Some("DESCRIPTION"));
+            drop(writer);
+            prop_assert!(data.is_empty());
+        }
+    }
+
+    #[test]
+    fn prop_parallel_text_matches_sequential(
+        records in prop::collection::vec(tx_record(LINE_DESCRIPTION), 1..16),
+        damage in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 0..4),
+        chunk_size in 1usize..512,
+    ) {
+        let mut data = Vec::new();
+        let mut writer = Format::Text.writer(&mut data);
+        writer.write_all(&records).unwrap();
+        writer.finish().unwrap();
+        drop(writer);
+        for (index, byte) in &damage {
+            let at = index.index(data.len());
+            data[at] = *byte;
+        }
+
+        // Неверный UTF-8 - ошибка строки, нестрогий режим пропускает её запись
+        let sequential = Format::Text
+            .reader_with(data.as_slice(), ParseMode::Lenient)
+            .read_report()
+            .unwrap();
+        let parallel = ParTextReader::with_mode(&data, ParseMode::Lenient)
+            .chunk_size(chunk_size)
+            .parse()
+            .unwrap();
+        prop_assert_eq!(parallel.data, sequential.data);
+        let lines = |errors: &[parsers::ParseError]| {
+            errors.iter().map(|e| e.position).collect::<Vec<_>>()
+        };
+        prop_assert_eq!(lines(&parallel.errors), lines(&sequential.errors));
+    }
+
+    #[test]
+    fn prop_bin_round_trip(records in prop::collection::vec(tx_record(ANY_DESCRIPTION), 0..8)) {
+        prop_assert_eq!(round_trip(Format::Bin, &records), records);
+    }
+
+    #[test]
+    fn prop_bin_v2_round_trip(records in prop::collection::vec(tx_record(ANY_DESCRIPTION), 0..8)) {
+        let mut writer = BinWriter::with_version(Vec::new(), BinVersion::V2);
+        writer.write_all(&records).unwrap();
+        let data = writer.into_inner().unwrap();
+        prop_assert_eq!(BinReader::new(data.as_slice()).read_all().unwrap(), records);
+    }
+
+    /// Любой перевёрнутый бит в записи версии 2 - ошибка, а не тихо изменённые данные
+    #[test]
+    fn prop_bin_v2_detects_bit_flips(
+        records in prop::collection::vec(tx_record(ANY_DESCRIPTION), 1..4),
+        index in any::<prop::sample::Index>(),
+        bit in 0..8u8,
+    ) {
+        let mut writer = BinWriter::with_version(Vec::new(), BinVersion::V2);
+        writer.write_all(&records).unwrap();
+        let mut data = writer.into_inner().unwrap();
+        let at = index.index(data.len());
+        data[at] ^= 1 << bit;
+
+        if let Ok(decoded) = BinReader::new(data.as_slice()).read_all() {
+            prop_assert_eq!(decoded, records);
+        }
+        let _ = BinReader::with_resync(data.as_slice()).read_all();
+    }
+
+    #[test]
+    fn prop_json_round_trip(records in prop::collection::vec(tx_record(ANY_DESCRIPTION), 0..8)) {
+        prop_assert_eq!(round_trip(Format::Json, &records), records);
+    }
+
+    #[test]
+    fn prop_readers_never_panic_on_damaged_records(
+        records in prop::collection::vec(tx_record(LINE_DESCRIPTION), 1..4),
+        damage in prop::collection::vec((any::<prop::sample::Index>(), any::<u8>()), 1..8),
+    ) {
+        for format in Format::ALL {
+            let mut data = Vec::new();
+            let mut writer = format.writer(&mut data);
+            writer.write_all(&records).unwrap();
+            writer.finish().unwrap();
+            drop(writer);
+
+            for (index, byte) in &damage {
+                let at = index.index(data.len());
+                data[at] = *byte;
+            }
+            for mode in [ParseMode::Strict, ParseMode::Lenient] {
+                let _ = format.reader_with(data.as_slice(), mode).read_report();
+            }
+        }
+    }
+
+    #[test]
+    fn prop_readers_never_panic(data in prop::collection::vec(any::<u8>(), 0..512)) {
+        for format in Format::ALL {
+            for mode in [ParseMode::Strict, ParseMode::Lenient] {
+                let _ = format.reader_with(data.as_slice(), mode).read_report();
+            }
+        }
+    }
+}